@@ -14,15 +14,31 @@ pub fn create_commands()
         commands::music::skip::skip(),
         commands::music::pause::pause(),
         commands::music::resume::resume(),
+        commands::music::seek::seek(),
+        commands::music::seek::forward(),
+        commands::music::seek::rewind(),
         commands::music::repeat::repeat(),
         commands::music::shuffle::shuffle(),
+        commands::music::fair::fair(),
+        commands::music::autoplay::autoplay(),
+        commands::music::crossfade::crossfade(),
+        commands::music::volume::volume(),
+        commands::music::filter::filter(),
+        commands::music::dj::dj(),
+        commands::music::playlist::playlist(),
         commands::music::search::search(),
         commands::music::remove::remove(),
+        commands::music::move_track::move_track(),
+        commands::music::swap::swap(),
+        commands::music::clear::clear(),
+        commands::music::dedupe::dedupe(),
         commands::test::button_test(),
         commands::test::pages(),
         commands::utils::capstone::capstone(),
         commands::utils::capstone::capinfo(),
+        commands::utils::lavalink_logs::lavalink_logs(),
         commands::utils::nano_chat::chat(),
+        commands::utils::settings::settings(),
     ];
     commands
 }
@@ -0,0 +1,29 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キューが空になったら関連する曲を自動で再生します
+pub async fn autoplay(
+    ctx: Context<'_>,
+    #[description = "自動再生を切り替えます"] option: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let mut q = ctx.data().queues.entry(guild_id).or_default();
+    q.value_mut().set_autoplay(option);
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
+    if option {
+        ctx.say(
+            "📻 自動再生を **ON** にしました。キューが空になると最後の曲に関連する曲を再生します",
+        )
+        .await?;
+    } else {
+        ctx.say("📻 自動再生を **OFF** にしました").await?;
+    }
+    Ok(())
+}
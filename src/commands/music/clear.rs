@@ -0,0 +1,27 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キューを空にします（再生中の曲はそのまま）
+pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Stop).await? {
+        return Ok(());
+    }
+    let removed = ctx
+        .data()
+        .queues
+        .get_mut(&gid)
+        .map(|mut q| q.clear())
+        .unwrap_or(0);
+    if removed == 0 {
+        ctx.say("📭 キューは空です").await?;
+        return Ok(());
+    }
+    ctx.data().store.mark_dirty(gid);
+    ctx.say(format!("🧹 キューから {removed} 曲を削除しました"))
+        .await?;
+    Ok(())
+}
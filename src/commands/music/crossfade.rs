@@ -0,0 +1,34 @@
+use crate::util::{
+    alias::{Context, Error},
+    crossfade::MAX_CROSSFADE_SECS,
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 曲間のクロスフェード秒数を設定します（0 で無効）
+pub async fn crossfade(
+    ctx: Context<'_>,
+    #[description = "フェード秒数 (0-12)"]
+    #[min = 0]
+    #[max = 12]
+    seconds: u64,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let secs = seconds.min(MAX_CROSSFADE_SECS);
+    let mut q = ctx.data().queues.entry(guild_id).or_default();
+    q.value_mut().set_crossfade(secs);
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
+    if secs == 0 {
+        ctx.say("🎚️ クロスフェードを **OFF** にしました").await?;
+    } else {
+        ctx.say(format!(
+            "🎚️ クロスフェードを **{secs} 秒** にしました（次の曲から適用）"
+        ))
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キュー内の重複した曲を削除します
+pub async fn dedupe(ctx: Context<'_>) -> Result<(), Error> {
+    let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let removed = ctx
+        .data()
+        .queues
+        .get_mut(&gid)
+        .map(|mut q| q.dedupe())
+        .unwrap_or(0);
+    if removed == 0 {
+        ctx.say("✅ 重複した曲はありませんでした").await?;
+        return Ok(());
+    }
+    ctx.data().store.mark_dirty(gid);
+    ctx.say(format!("🧹 重複した {removed} 曲を削除しました"))
+        .await?;
+    Ok(())
+}
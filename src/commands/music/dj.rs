@@ -0,0 +1,122 @@
+use poise::serenity_prelude::Role;
+
+use crate::util::alias::{Context, Error};
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("role", "clear", "requester", "voteskip", "show"),
+    subcommand_required,
+    required_permissions = "MANAGE_GUILD"
+)]
+/// DJ ロールと音楽操作の権限を設定します
+pub async fn dj(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// DJ ロールを設定します（停止・割り込み・他人の曲のスキップなどが可能）
+pub async fn role(
+    ctx: Context<'_>, #[description = "DJ ロール"] role: Role
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.dj_role = Some(role.id.get()))
+        .await?;
+    ctx.say(format!("🎧 DJ ロールを <@&{}> に設定しました", role.id))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// DJ ロールを解除します（全員が操作可能になります）
+pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.dj_role = None)
+        .await?;
+    ctx.say("🎧 DJ ロールを解除しました（全員が操作できます）")
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// リクエストした本人が自分の曲をスキップ・削除できるかを設定します
+pub async fn requester(
+    ctx: Context<'_>,
+    #[description = "許可する場合は true"] allow: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.requester_can_manage = allow)
+        .await?;
+    let status = if allow { "許可" } else { "禁止" };
+    ctx.say(format!(
+        "🎧 リクエスト者による自分の曲のスキップ・削除を **{status}** しました"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// DJ 以外のスキップを投票制にします
+pub async fn voteskip(
+    ctx: Context<'_>,
+    #[description = "投票制にする場合は true"] enabled: bool,
+    #[description = "スキップに必要な賛成の割合 % (既定: 50)"]
+    #[min = 1]
+    #[max = 100]
+    percent: Option<u8>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let settings = ctx
+        .data()
+        .settings
+        .update(guild_id, |s| {
+            s.vote_skip = enabled;
+            if let Some(p) = percent {
+                s.vote_skip_ratio = f64::from(p.clamp(1, 100)) / 100.0;
+            }
+        })
+        .await?;
+    if enabled {
+        ctx.say(format!(
+            "🗳️ 投票スキップを **ON** にしました（聴いている人の {:.0}% の賛成でスキップ）",
+            settings.vote_skip_ratio * 100.0
+        ))
+        .await?;
+    } else {
+        ctx.say("🗳️ 投票スキップを **OFF** にしました").await?;
+    }
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 現在の権限設定を表示します
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let settings = ctx.data().settings.get(guild_id);
+    let role = settings
+        .dj_role()
+        .map(|r| format!("<@&{r}>"))
+        .unwrap_or_else(|| "未設定（全員が DJ）".to_string());
+    let requester = if settings.requester_can_manage {
+        "許可"
+    } else {
+        "禁止"
+    };
+    let vote = if settings.vote_skip {
+        format!("ON ({:.0}%)", settings.vote_skip_ratio * 100.0)
+    } else {
+        "OFF".to_string()
+    };
+    ctx.say(format!(
+        "🎧 DJ ロール: {role}\n👤 リクエスト者による自分の曲のスキップ・削除: {requester}\n🗳️ 投票スキップ: {vote}\n🛡️ 管理者（サーバー管理権限）は常にすべて操作できます"
+    ))
+    .await?;
+    Ok(())
+}
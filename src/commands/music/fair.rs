@@ -0,0 +1,35 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// リクエストした人ごとに順番に再生する公平モードを切り替えます
+pub async fn fair(
+    ctx: Context<'_>,
+    #[description = "公平モードを切り替えます"] option: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let mut q = ctx.data().queues.entry(guild_id).or_default();
+    let was_shuffled = q.config.shuffle;
+    q.value_mut().set_fair(option);
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
+    if option {
+        let note = if was_shuffled {
+            "（シャッフルは OFF にしました）"
+        } else {
+            ""
+        };
+        ctx.say(format!(
+            "⚖️ 公平モードを **ON** にしました。リクエストした人ごとに順番に再生します{note}"
+        ))
+        .await?;
+    } else {
+        ctx.say("⚖️ 公平モードを **OFF** にしました").await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use poise::ChoiceParameter;
+
+use crate::util::{
+    alias::{Context, Error},
+    filters::{EQ_BANDS, FilterPreset, guild_filters, parse_equalizer},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// エフェクト（フィルター）を切り替えます
+pub async fn filter(
+    ctx: Context<'_>,
+    #[description = "プリセット"] preset: FilterPreset,
+    #[description = "Custom EQ 用のゲイン (カンマ区切り, バンド 0 から最大 15 個, -0.25〜1.0)"]
+    eq: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?; // 3秒ルール
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+
+    let custom_eq = match (preset, eq.as_deref()) {
+        (FilterPreset::Custom, Some(eq)) => match parse_equalizer(eq) {
+            Some(gains) => gains,
+            None => {
+                ctx.say(format!(
+                    "❌ EQ は -0.25〜1.0 の数値を最大 {EQ_BANDS} 個、カンマ区切りで指定してください"
+                ))
+                .await?;
+                return Ok(());
+            }
+        },
+        (FilterPreset::Custom, None) => {
+            ctx.say("❌ Custom EQ には `eq` を指定してください（例: `0.2,0.15,0.1`）")
+                .await?;
+            return Ok(());
+        }
+        _ => Vec::new(),
+    };
+
+    let mut q = ctx.data().queues.entry(guild_id).or_default();
+    q.value_mut().set_filter(preset, custom_eq);
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
+
+    if let Some(player) = ctx
+        .data()
+        .lavalink
+        .as_ref()
+        .and_then(|l| l.get_player_context(guild_id))
+    {
+        player
+            .set_filters(guild_filters(&ctx.data().queues, guild_id, None))
+            .await
+            .map_err(|e| Error::from(format!("failed to set Lavalink filters: {e}")))?;
+    }
+
+    if preset == FilterPreset::Off {
+        ctx.say("🎛️ フィルターを解除しました").await?;
+    } else {
+        ctx.say(format!("🎛️ フィルターを **{}** にしました", preset.name()))
+            .await?;
+    }
+    Ok(())
+}
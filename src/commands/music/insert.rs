@@ -1,16 +1,33 @@
+use poise::CreateReply;
+
 use crate::util::{
     alias::{Context, Error},
+    music_ui::queue_error_embed,
+    permissions::{MusicAction, require},
     track::TrackRequest,
 };
 
 #[poise::command(slash_command, guild_only)]
 pub async fn insert(ctx: Context<'_>, #[rest] url: String) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Insert).await? {
+        return Ok(());
+    }
+    let limits = ctx.data().settings.get(guild_id).queue_limits();
     // Data から queues を取得
     let queues = ctx.data().queues.clone(); // Arc<DashMap<…>>
     // entry.or_default() でそのギルドの MusicQueue を初期化
-    let mut q = queues.entry(guild_id).or_default();
-    q.push_front(TrackRequest::new(url, ctx.author().id));
+    let req = TrackRequest::new(url, ctx.author().id);
+    let pushed = queues
+        .entry(guild_id)
+        .or_default()
+        .try_push_front(req.clone(), &limits);
+    if let Err(err) = pushed {
+        ctx.send(CreateReply::default().embed(queue_error_embed(Some(&req), &err)))
+            .await?;
+        return Ok(());
+    }
+    ctx.data().store.mark_dirty(guild_id);
     ctx.say("優先再生キュー（先頭）に追加しました").await?;
     Ok(())
 }
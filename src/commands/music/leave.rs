@@ -16,6 +16,9 @@ pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
 
     delete_player(&lavalink, guild_id).await?;
     ctx.data().lavalink_playing.remove(&guild_id);
+    ctx.data().prefetched.remove(&guild_id);
+    ctx.data().fades.remove(&guild_id);
+    ctx.data().store.mark_dirty(guild_id);
 
     if let Some(call) = manager.get(guild_id) {
         call.lock().await.leave().await?;
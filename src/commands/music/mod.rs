@@ -1,15 +1,27 @@
+pub mod autoplay;
+pub mod clear;
+pub mod crossfade;
+pub mod dedupe;
+pub mod dj;
+pub mod fair;
+pub mod filter;
 pub mod insert;
 pub mod join;
 pub mod leave;
+pub mod move_track;
 pub mod pause;
 pub mod play;
 pub mod play_lavalink;
+pub mod playlist;
 pub mod queue;
 pub mod remove;
 pub mod repeat;
 pub mod resume;
 pub mod search;
+pub mod seek;
 pub mod shuffle;
 pub mod skip;
 pub mod skip_lavalink;
 pub mod stop;
+pub mod swap;
+pub mod volume;
@@ -0,0 +1,44 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only, rename = "move")]
+/// キューの曲を別の位置へ移動します
+pub async fn move_track(
+    ctx: Context<'_>,
+    #[description = "移動する曲の位置 (1〜)"] from: usize,
+    #[description = "移動先の位置 (1〜)"] to: usize,
+) -> Result<(), Error> {
+    let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let result = {
+        let mut q = ctx.data().queues.entry(gid).or_default();
+        if q.config.fair {
+            Err("❌ 公平モード中は並べ替えできません（`/fair false` で解除）".to_string())
+        } else {
+            let len = q.len();
+            let indices = from
+                .checked_sub(1)
+                .and_then(|i| q.index_at(i))
+                .zip(to.checked_sub(1).and_then(|i| q.index_at(i)));
+            indices
+                .and_then(|(a, b)| q.move_item(a, b))
+                .map(|tr| tr.meta.title.clone().unwrap_or_else(|| tr.url.clone()))
+                .ok_or_else(|| format!("❌ 有効な範囲は 1〜{len} です"))
+        }
+    };
+    match result {
+        Ok(title) => {
+            ctx.data().store.mark_dirty(gid);
+            ctx.say(format!("↕️ **{title}** を #{to} に移動しました"))
+                .await?;
+        }
+        Err(msg) => {
+            ctx.say(msg).await?;
+        }
+    }
+    Ok(())
+}
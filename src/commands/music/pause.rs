@@ -1,6 +1,7 @@
 use crate::util::{
     alias::{Context, Error},
     lavalink_player::pause_current_lavalink,
+    permissions::{MusicAction, require},
     player::PlaybackControlResult,
 };
 
@@ -9,6 +10,9 @@ pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer().await?; // ← 3秒ルール
 
     let guild_id = ctx.guild_id().unwrap();
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
     let lavalink = ctx
         .data()
         .lavalink
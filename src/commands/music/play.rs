@@ -1,12 +1,90 @@
-use crate::{Error, util::alias::Context};
+use crate::{
+    Data, Error,
+    util::{
+        alias::Context,
+        playlist::{PlaylistOptions, PlaylistRange},
+        search_source::{SearchSource, autocomplete_source},
+        suggest::autocomplete_query,
+    },
+};
 
-#[poise::command(slash_command, prefix_command, guild_only)]
-pub async fn play(
+/// `/play` と `s!play` をまとめたコマンド。
+/// プレフィックス版は位置で引数を取ると検索語の先頭を範囲やフラグと取り違えるので、
+/// 検索語だけを受け取り、範囲などは後ろに `range:20-60` の形で書いてもらう。
+pub fn play() -> poise::Command<Data, Error> {
+    let mut command = play_slash();
+    command.prefix_action = play_prefix().prefix_action;
+    command
+}
+
+#[poise::command(slash_command, guild_only, rename = "play")]
+pub async fn play_slash(
+    ctx: Context<'_>,
+    #[autocomplete = "autocomplete_query"]
+    #[description = "URL (YouTube / Spotify / Apple Music / Deezer) または検索語 (空で再開)"]
+    query: Option<String>,
+    #[description = "プレイリストから追加する範囲 (例: 20-60, 20-)"] range: Option<PlaylistRange>,
+    #[description = "プレイリストをシャッフルして追加"]
+    #[flag]
+    shuffle: bool,
+    #[description = "検索ソース（省略でサーバーの既定）"]
+    #[autocomplete = "autocomplete_source"]
+    source: Option<SearchSource>,
+) -> Result<(), Error> {
+    let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let options = PlaylistOptions { range, shuffle };
+    crate::commands::music::play_lavalink::run(&ctx, gid, query, options, source).await
+}
+
+#[poise::command(prefix_command, guild_only, rename = "play")]
+async fn play_prefix(
     ctx: Context<'_>,
     #[rest]
-    #[description = "YouTube URL または検索語 (空で再開)"]
+    #[description = "URL または検索語。後ろに range:20-60 / shuffle:true / source:ytmsearch を付けられます"]
     query: Option<String>,
 ) -> Result<(), Error> {
     let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
-    crate::commands::music::play_lavalink::run(&ctx, gid, query).await
+    let (query, options, source) = match query.as_deref().map(split_prefix_options) {
+        None => (None, PlaylistOptions::default(), None),
+        Some(Ok(parsed)) => parsed,
+        Some(Err(message)) => {
+            ctx.say(format!("❌ {message}")).await?;
+            return Ok(());
+        }
+    };
+    crate::commands::music::play_lavalink::run(&ctx, gid, query, options, source).await
+}
+
+type PrefixArgs = (Option<String>, PlaylistOptions, Option<SearchSource>);
+
+/// 検索語の後ろに並んだ `range:` / `shuffle:` / `source:` を取り出す。
+/// 知らないキーの語（`https://...` など）に当たったところからは検索語として扱う。
+fn split_prefix_options(raw: &str) -> Result<PrefixArgs, String> {
+    let mut words: Vec<&str> = raw.split_whitespace().collect();
+    let mut options = PlaylistOptions::default();
+    let mut source = None;
+    while let Some((key, value)) = words.last().and_then(|w| w.split_once(':')) {
+        match key.to_ascii_lowercase().as_str() {
+            "range" => {
+                options.range = Some(value.parse::<PlaylistRange>().map_err(|e| e.to_string())?)
+            }
+            "shuffle" => {
+                options.shuffle = match value.to_ascii_lowercase().as_str() {
+                    "true" | "on" | "yes" => true,
+                    "false" | "off" | "no" => false,
+                    _ => {
+                        return Err(
+                            "シャッフルは `shuffle:true` / `shuffle:false` で指定してください"
+                                .to_string(),
+                        );
+                    }
+                }
+            }
+            "source" => source = Some(value.parse::<SearchSource>().map_err(|e| e.to_string())?),
+            _ => break,
+        }
+        words.pop();
+    }
+    let query = Some(words.join(" ")).filter(|q| !q.is_empty());
+    Ok((query, options, source))
 }
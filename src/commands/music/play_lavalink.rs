@@ -4,15 +4,22 @@ use crate::{
     util::{
         alias::Context,
         lavalink_player::{
-            current_play_mode, pause_current_lavalink, play_next_from_queue_lavalink,
-            play_track_req_lavalink, resume_current_lavalink, stop_and_clear_lavalink,
+            apply_track_metadata, current_play_mode, pause_current_lavalink,
+            play_next_from_queue_lavalink, play_next_or_autoplay, play_track_req_lavalink_at,
+            resume_current_lavalink, seek_current_lavalink, stop_and_clear_lavalink,
         },
-        music_ui::{control_components, track_embed},
-        player::{ManualTransitionGuard, PlaybackControlResult},
-        playlist,
-        queue::MusicQueue,
+        music_ui::{
+            batch_skip_note, control_components, queue_error_embed, track_embed, track_embed_at,
+        },
+        panel,
+        permissions::{MusicAction, check_interaction},
+        player::{ManualTransitionGuard, PlaybackControlResult, SeekResult, SeekTarget},
+        playlist::{self, PlaylistContext, PlaylistOptions},
+        queue::{MusicQueue, QueueError},
+        search_source::{self, SearchSource},
         track::TrackRequest,
         types::LavalinkPlayingMap,
+        vote::{VoteOutcome, cast_vote},
     },
 };
 use dashmap::DashMap;
@@ -28,27 +35,33 @@ use std::{
     sync::Arc,
     time::{Duration, Instant},
 };
+use url::Url;
 
 const ACCENT: Colour = Colour::new(0x5865F2);
 const SUCCESS: Colour = Colour::new(0x2ECC71);
 const WARNING: Colour = Colour::new(0xF1C40F);
 const DANGER: Colour = Colour::new(0xE74C3C);
-const CONTROL_IDLE_TIMEOUT: Duration = Duration::from_secs(1800);
-const MAX_PLAYLIST_ITEMS: usize = 50;
+const SEEK_STEP: Duration = Duration::from_secs(10);
 
-fn playlist_pages(urls: &[String], title: &str) -> Vec<String> {
+fn playlist_pages(reqs: &[TrackRequest], title: &str) -> Vec<String> {
     const PAGE_SIZE: usize = 10;
-    urls.chunks(PAGE_SIZE)
+    reqs.chunks(PAGE_SIZE)
         .enumerate()
         .map(|(pi, chunk)| {
             let mut s = format!(
                 "📃 {title} ({}/{})\n\n",
                 pi + 1,
-                (urls.len() + PAGE_SIZE - 1) / PAGE_SIZE
+                (reqs.len() + PAGE_SIZE - 1) / PAGE_SIZE
             );
-            for (i, url) in chunk.iter().enumerate() {
+            for (i, req) in chunk.iter().enumerate() {
                 let idx = pi * PAGE_SIZE + i + 1;
-                s.push_str(&format!("{idx}. {url}\n"));
+                // 外部サービスから展開した曲は URL がまだ無いので曲名を出す
+                let label = match (&req.meta.artist, &req.meta.title) {
+                    (Some(artist), Some(title)) => format!("{artist} - {title}"),
+                    (None, Some(title)) => title.clone(),
+                    _ => req.url.clone(),
+                };
+                s.push_str(&format!("{idx}. {label}\n"));
             }
             s
         })
@@ -67,6 +80,9 @@ async fn send_control_message(
     let handle = ctx.send(reply).await?;
     let msg = handle.message().await?.into_owned();
     ctx.data().now_playing.insert(gid, (msg.channel_id, msg.id));
+    if let Some(lavalink) = ctx.data().lavalink.clone() {
+        panel::ensure_updater(lavalink, gid);
+    }
     Ok(msg)
 }
 
@@ -105,7 +121,8 @@ async fn handle_controls(
     lavalink: Arc<LavalinkClient>,
     mut msg: Message,
 ) -> Result<(), Error> {
-    let mut deadline = Instant::now() + CONTROL_IDLE_TIMEOUT;
+    let idle_timeout = ctx.data().settings.get(gid).panel_timeout();
+    let mut deadline = Instant::now() + idle_timeout;
     loop {
         let now = Instant::now();
         if now >= deadline {
@@ -117,12 +134,48 @@ async fn handle_controls(
             break;
         };
 
-        if interaction.user.id != ctx.author().id {
-            respond_ephemeral(&ctx, &interaction, "この操作はコマンド実行者のみ可能です").await;
-            continue;
+        let action = match interaction.data.custom_id.as_str() {
+            "music_stop" => Some(MusicAction::Stop),
+            "music_skip" => Some(MusicAction::Skip {
+                requester: playing.get(&gid).map(|e| e.value().requested_by),
+            }),
+            "music_pause" | "music_resume" | "music_rewind" | "music_forward" => {
+                Some(MusicAction::Control)
+            }
+            _ => None,
+        };
+        if let Some(action) = action {
+            if let Err(reason) = check_interaction(ctx, gid, &interaction, action) {
+                let vote_skip = matches!(action, MusicAction::Skip { .. })
+                    && ctx.data().settings.get(gid).vote_skip;
+                if !vote_skip {
+                    respond_ephemeral(&ctx, &interaction, &reason).await;
+                    continue;
+                }
+                match cast_vote(ctx.cache(), ctx.data(), gid, interaction.user.id) {
+                    VoteOutcome::Passed => {}
+                    outcome @ VoteOutcome::Recorded { .. } => {
+                        // パネルに投票数を表示する
+                        let current = playing.get(&gid).map(|e| e.value().clone());
+                        let mode = current_play_mode(&lavalink, gid).await;
+                        let embed = track_embed(
+                            "🎵 再生中",
+                            current.as_ref(),
+                            Some(outcome.message()),
+                            SUCCESS,
+                        );
+                        update_message(&ctx, &interaction, embed, control_components(mode)).await;
+                        continue;
+                    }
+                    outcome => {
+                        respond_ephemeral(&ctx, &interaction, &outcome.message()).await;
+                        continue;
+                    }
+                }
+            }
         }
 
-        deadline = Instant::now() + CONTROL_IDLE_TIMEOUT;
+        deadline = Instant::now() + idle_timeout;
 
         match interaction.data.custom_id.as_str() {
             "music_stop" => {
@@ -175,6 +228,32 @@ async fn handle_controls(
                     respond_ephemeral(&ctx, &interaction, "再生中の曲がありません").await;
                 }
             },
+            id @ ("music_rewind" | "music_forward") => {
+                let target = if id == "music_rewind" {
+                    SeekTarget::Backward(SEEK_STEP)
+                } else {
+                    SeekTarget::Forward(SEEK_STEP)
+                };
+                match seek_current_lavalink(&lavalink, gid, &playing, target).await? {
+                    SeekResult::Moved(req, position, _) => {
+                        let mode = current_play_mode(&lavalink, gid).await;
+                        let title = if id == "music_rewind" {
+                            "⏪ 10秒戻しました"
+                        } else {
+                            "⏩ 10秒進めました"
+                        };
+                        let embed =
+                            track_embed_at(title, Some(&req), None, SUCCESS, Some(position));
+                        update_message(&ctx, &interaction, embed, control_components(mode)).await;
+                    }
+                    SeekResult::NotSeekable => {
+                        respond_ephemeral(&ctx, &interaction, "この曲はシークできません").await;
+                    }
+                    SeekResult::Missing => {
+                        respond_ephemeral(&ctx, &interaction, "再生中の曲がありません").await;
+                    }
+                }
+            }
             "music_skip" => {
                 let embed = track_embed("⏳ 次の曲を準備しています…", None, None, ACCENT);
                 update_message(&ctx, &interaction, embed, Vec::new()).await;
@@ -183,20 +262,13 @@ async fn handle_controls(
                 if let Some(player) = lavalink.get_player_context(gid) {
                     let _ = player.stop_now().await;
                 }
-                playing.remove(&gid);
+                let skipped = playing.remove(&gid).map(|(_, req)| req);
+                ctx.data().store.mark_dirty(gid);
 
-                let res = play_next_from_queue_lavalink(
-                    gid,
-                    lavalink.clone(),
-                    queues.clone(),
-                    playing.clone(),
-                    ctx.data().history.clone(),
-                    3,
-                )
-                .await?;
+                let res = play_next_or_autoplay(gid, lavalink.clone(), skipped).await?;
 
                 if let Some(started_req) = res.started {
-                    let info = if res.skipped > 0 {
+                    let mut info = if res.skipped > 0 {
                         format!(
                             "再生失敗 {} 件をスキップ / キュー残り {} 件",
                             res.skipped, res.remaining
@@ -204,6 +276,9 @@ async fn handle_controls(
                     } else {
                         format!("キュー残り {} 件", res.remaining)
                     };
+                    if res.autoplayed {
+                        info.insert_str(0, "📻 自動再生 / ");
+                    }
                     let embed = track_embed(
                         "⏭ 次の曲を再生しました",
                         Some(&started_req),
@@ -250,7 +325,158 @@ async fn handle_controls(
     Ok(())
 }
 
-pub async fn run(ctx: &Context<'_>, gid: GuildId, query: Option<String>) -> Result<(), Error> {
+/// 展開したプレイリストを、再生中ならキューへ追加し、停止中なら先頭から再生する。
+/// `start_at` は先頭から再生するときの 1 曲目の開始位置。
+async fn play_expanded(
+    ctx: &Context<'_>,
+    gid: GuildId,
+    lavalink: Arc<LavalinkClient>,
+    mut reqs: Vec<TrackRequest>,
+    start_at: Option<Duration>,
+    current_state: PlayMode,
+) -> Result<(), Error> {
+    let queues = ctx.data().queues.clone();
+    let playing = ctx.data().lavalink_playing.clone();
+    let limits = ctx.data().settings.get(gid).queue_limits();
+    let pages = playlist_pages(&reqs, "プレイリスト展開結果");
+    let page_slices: Vec<&str> = pages.iter().map(String::as_str).collect();
+
+    let total = reqs.len();
+    let preview = reqs
+        .first()
+        .cloned()
+        .ok_or_else(|| Error::from("プレイリストが空でした"))?;
+
+    if current_state == PlayMode::Play {
+        let outcome = queues.entry(gid).or_default().extend_checked(reqs, &limits);
+        let Some(position_start) = outcome.first_position else {
+            if let Some(reason) = &outcome.reason {
+                let embed = queue_error_embed(Some(&preview), reason);
+                ctx.send(CreateReply::default().embed(embed)).await?;
+            }
+            return Ok(());
+        };
+        let position_end = position_start + outcome.added - 1;
+        ctx.data().store.mark_dirty(gid);
+
+        let mut note = format!(
+            "{total} 件を展開しました。キュー #{position_start}〜#{position_end} に追加しました。"
+        );
+        if let Some(skipped) = batch_skip_note(&outcome) {
+            note.push('\n');
+            note.push_str(&skipped);
+        }
+        let embed = track_embed(
+            "📃 プレイリストをキューに追加しました",
+            Some(&preview),
+            Some(note),
+            ACCENT,
+        );
+        let msg = send_control_message(ctx, gid, embed, current_state).await?;
+        handle_controls(
+            ctx,
+            gid,
+            queues.clone(),
+            playing.clone(),
+            lavalink.clone(),
+            msg,
+        )
+        .await?;
+        paginate(*ctx, &page_slices).await?;
+        return Ok(());
+    } else {
+        // すぐ再生する 1 曲目も単曲のときと同じく曲自体の制限を確認し、掛かった曲は飛ばす
+        let mut rejected: Option<(TrackRequest, QueueError)> = None;
+        let mut skipped_first = 0;
+        let first = loop {
+            if reqs.is_empty() {
+                break None;
+            }
+            let req = reqs.remove(0);
+            match limits.check_track(&req) {
+                Ok(()) => break Some(req),
+                Err(err) => {
+                    skipped_first += 1;
+                    if rejected.is_none() {
+                        rejected = Some((req, err));
+                    }
+                }
+            }
+        };
+        let Some(first) = first else {
+            if let Some((req, err)) = &rejected {
+                let embed = queue_error_embed(Some(req), err);
+                ctx.send(CreateReply::default().embed(embed)).await?;
+            }
+            return Ok(());
+        };
+        // 開始位置は URL で指定された 1 曲目に対するもの
+        let start_at = start_at.filter(|_| skipped_first == 0);
+        let mut outcome = queues.entry(gid).or_default().extend_checked(reqs, &limits);
+        outcome.skipped += skipped_first;
+        if let Some((_, err)) = rejected {
+            outcome.reason = Some(err);
+        }
+        ctx.data().store.mark_dirty(gid);
+
+        match play_track_req_lavalink_at(
+            gid,
+            lavalink.clone(),
+            playing.clone(),
+            ctx.data().history.clone(),
+            first,
+            start_at,
+        )
+        .await
+        {
+            Ok(started_req) => {
+                let remaining = queues.get(&gid).map(|q| q.len()).unwrap_or(0);
+                let mut note =
+                    format!("プレイリスト {total} 件を展開しました。キュー残り {remaining} 件");
+                if let Some(skipped) = batch_skip_note(&outcome) {
+                    note.push('\n');
+                    note.push_str(&skipped);
+                }
+                let embed = track_embed(
+                    "🎶 再生を開始しました",
+                    Some(&started_req),
+                    Some(note),
+                    SUCCESS,
+                );
+                let msg = send_control_message(ctx, gid, embed, PlayMode::Play).await?;
+                handle_controls(
+                    ctx,
+                    gid,
+                    queues.clone(),
+                    playing.clone(),
+                    lavalink.clone(),
+                    msg,
+                )
+                .await?;
+                paginate(*ctx, &page_slices).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                let embed = track_embed(
+                    "❌ 再生開始に失敗しました",
+                    None,
+                    Some(format!("{e}")),
+                    DANGER,
+                );
+                let _ = ctx.send(CreateReply::default().embed(embed)).await;
+                return Ok(());
+            }
+        }
+    }
+}
+
+pub async fn run(
+    ctx: &Context<'_>,
+    gid: GuildId,
+    query: Option<String>,
+    options: PlaylistOptions,
+    source: Option<SearchSource>,
+) -> Result<(), Error> {
     let lavalink = ctx
         .data()
         .lavalink
@@ -292,111 +518,39 @@ pub async fn run(ctx: &Context<'_>, gid: GuildId, query: Option<String>) -> Resu
     }
 
     if let Some(q) = query {
-        if playlist::is_youtube_playlist_url(&q) {
-            match playlist::expand_youtube_playlist(&q, MAX_PLAYLIST_ITEMS).await {
-                Ok(urls) => {
-                    let pages = playlist_pages(&urls, "プレイリスト展開結果");
-                    let page_slices: Vec<&str> = pages.iter().map(String::as_str).collect();
-
-                    let mut reqs = urls
-                        .into_iter()
-                        .map(|u| TrackRequest::new(u, author))
-                        .collect::<Vec<_>>();
-                    let total = reqs.len();
-                    let preview = reqs
-                        .first()
-                        .cloned()
-                        .ok_or_else(|| Error::from("プレイリストが空でした"))?;
-
-                    if current_state == PlayMode::Play {
-                        let (position_start, position_end) = {
-                            let mut guard = queues.entry(gid).or_default();
-                            let start = guard.len() + 1;
-                            for r in reqs {
-                                guard.push_back(r);
-                            }
-                            let end = start + total.saturating_sub(1);
-                            (start, end)
-                        };
-
-                        let embed = track_embed(
-                            "📃 プレイリストをキューに追加しました",
-                            Some(&preview),
-                            Some(format!(
-                                "{total} 件を展開しました。キュー #{position_start}〜#{position_end} に追加しました。"
-                            )),
-                            ACCENT,
-                        );
-                        let msg = send_control_message(ctx, gid, embed, current_state).await?;
-                        handle_controls(
-                            ctx,
-                            gid,
-                            queues.clone(),
-                            playing.clone(),
-                            lavalink.clone(),
-                            msg,
-                        )
-                        .await?;
-                        paginate(*ctx, &page_slices).await?;
-                        return Ok(());
-                    } else {
-                        let first = reqs.remove(0);
-                        {
-                            let mut guard = queues.entry(gid).or_default();
-                            for r in reqs {
-                                guard.push_back(r);
-                            }
-                        }
-
-                        match play_track_req_lavalink(
-                            gid,
-                            lavalink.clone(),
-                            playing.clone(),
-                            ctx.data().history.clone(),
-                            first,
-                        )
-                        .await
-                        {
-                            Ok(started_req) => {
-                                let remaining = queues.get(&gid).map(|q| q.len()).unwrap_or(0);
-                                let embed = track_embed(
-                                    "🎶 再生を開始しました",
-                                    Some(&started_req),
-                                    Some(format!(
-                                        "プレイリスト {total} 件を展開しました。キュー残り {remaining} 件"
-                                    )),
-                                    SUCCESS,
-                                );
-                                let msg =
-                                    send_control_message(ctx, gid, embed, PlayMode::Play).await?;
-                                handle_controls(
-                                    ctx,
-                                    gid,
-                                    queues.clone(),
-                                    playing.clone(),
-                                    lavalink.clone(),
-                                    msg,
-                                )
-                                .await?;
-                                paginate(*ctx, &page_slices).await?;
-                                return Ok(());
-                            }
-                            Err(e) => {
-                                let embed = track_embed(
-                                    "❌ 再生開始に失敗しました",
-                                    None,
-                                    Some(format!("{e}")),
-                                    DANGER,
-                                );
-                                let _ = ctx.send(CreateReply::default().embed(embed)).await;
-                                return Ok(());
-                            }
-                        }
-                    }
+        let limits = ctx.data().settings.get(gid).queue_limits();
+        let mut resolved = None;
+        // URL の `t` で指定された開始位置（すぐ再生するときだけ使う）
+        let mut start_at = None;
+        if let Some(provider) = playlist::find_provider(&q) {
+            ctx.defer().await?;
+            let cx = PlaylistContext {
+                lavalink: Some(&*lavalink),
+                guild_id: gid,
+                requested_by: author,
+            };
+            let cap = ctx.data().settings.get(gid).playlist_limit;
+            match playlist::expand_with_options(provider, &cx, &q, options, cap).await {
+                Ok(mut expanded) if expanded.requests.len() == 1 => {
+                    start_at = expanded.start_at;
+                    resolved = Some(expanded.requests.remove(0));
+                }
+                // 展開できなかった URL は通常の 1 曲として扱う
+                Ok(expanded) if expanded.requests.is_empty() => {}
+                Ok(expanded) => {
+                    return play_expanded(
+                        ctx,
+                        gid,
+                        lavalink,
+                        expanded.requests,
+                        expanded.start_at,
+                        current_state,
+                    )
+                    .await;
                 }
                 Err(e) => {
                     let embed = track_embed(
-                        "❌ プレイリスト展開に失敗しました",
+                        &format!("❌ {} を読み込めませんでした", provider.name()),
                         None,
                         Some(e.to_string()),
                         DANGER,
@@ -407,15 +561,67 @@ pub async fn run(ctx: &Context<'_>, gid: GuildId, query: Option<String>) -> Resu
             }
         }
 
-        match TrackRequest::from_url(q, author).await {
+        // ソースを指定した検索語はその場で検索し、最初の曲を使う
+        if resolved.is_none() {
+            if let Some(source) = source.filter(|_| Url::parse(q.trim()).is_err()) {
+                ctx.defer().await?;
+                let found = search_source::search(&lavalink, gid, source, &q).await;
+                match found.as_ref().map(|tracks| tracks.first()) {
+                    Ok(Some(track)) => {
+                        let mut req = TrackRequest::new(q.clone(), author);
+                        apply_track_metadata(&mut req, track);
+                        resolved = Some(req);
+                    }
+                    Ok(None) => {
+                        let embed = track_embed(
+                            &format!("❌ {} で見つかりませんでした", source.label()),
+                            None,
+                            Some(q.clone()),
+                            DANGER,
+                        );
+                        ctx.send(CreateReply::default().embed(embed)).await?;
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        let embed = track_embed(
+                            &format!("❌ {} で検索できませんでした", source.label()),
+                            None,
+                            Some(e.to_string()),
+                            DANGER,
+                        );
+                        ctx.send(CreateReply::default().embed(embed)).await?;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        let resolved = match resolved {
+            Some(req) => Ok(req),
+            None => TrackRequest::from_url(q, author).await,
+        };
+        match resolved {
             Ok(req) => {
-                if current_state == PlayMode::Play {
-                    let position = {
-                        let mut guard = queues.entry(gid).or_default();
-                        let pos = guard.len() + 1;
-                        guard.push_back(req.clone());
-                        pos
-                    };
+                let checked = if current_state == PlayMode::Play {
+                    queues
+                        .entry(gid)
+                        .or_default()
+                        .try_push_back(req.clone(), &limits)
+                        .map(Some)
+                } else {
+                    // すぐ再生する場合はキューに入らないので、曲自体の制限だけ確認する
+                    limits.check_track(&req).map(|_| None)
+                };
+                let queued_at = match checked {
+                    Ok(position) => position,
+                    Err(err) => {
+                        let embed = queue_error_embed(Some(&req), &err);
+                        ctx.send(CreateReply::default().embed(embed)).await?;
+                        return Ok(());
+                    }
+                };
+                if let Some(position) = queued_at {
+                    ctx.data().store.mark_dirty(gid);
                     let embed = track_embed(
                         "📥 キューに追加しました",
                         Some(&req),
@@ -436,12 +642,13 @@ pub async fn run(ctx: &Context<'_>, gid: GuildId, query: Option<String>) -> Resu
                     .await?;
                     return Ok(());
                 } else {
-                    match play_track_req_lavalink(
+                    match play_track_req_lavalink_at(
                         gid,
                         lavalink.clone(),
                         playing.clone(),
                         ctx.data().history.clone(),
                         req,
+                        start_at,
                     )
                     .await
                     {
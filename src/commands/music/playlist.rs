@@ -0,0 +1,481 @@
+use poise::serenity_prelude::{Attachment, Colour, CreateAttachment};
+use poise::{ChoiceParameter, CreateReply};
+
+use crate::{
+    commands::music::queue::try_autostart_from_queue,
+    util::{
+        alias::{Context, Error},
+        music_ui::{batch_skip_note, queue_error_embed, track_embed},
+        permissions::{MusicAction, require},
+        saved_playlists::{
+            MAX_PLAYLIST_TRACKS, MAX_PLAYLISTS, PlaylistOwner, PlaylistScope, SavedPlaylist,
+            normalize_name,
+        },
+        storage::StoredTrack,
+        track::TrackRequest,
+    },
+};
+
+const ACCENT: Colour = Colour::new(0x5865F2);
+const SUCCESS: Colour = Colour::new(0x2ECC71);
+/// 取り込むファイルの最大サイズ。
+const MAX_IMPORT_BYTES: u32 = 1024 * 1024;
+
+#[derive(Copy, Clone, Debug, Default, ChoiceParameter)]
+pub enum PlaylistSource {
+    #[default]
+    #[name = "キュー（再生中の曲を含む）"]
+    Queue,
+    #[name = "再生履歴"]
+    History,
+}
+
+#[derive(Copy, Clone, Debug, Default, ChoiceParameter)]
+pub enum ExportFormat {
+    #[default]
+    #[name = "JSON"]
+    Json,
+    #[name = "M3U"]
+    M3u,
+}
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "save", "load", "list", "show", "add", "remove", "delete", "export", "import"
+    ),
+    subcommand_required
+)]
+/// 保存したプレイリストを管理します
+pub async fn playlist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+fn owner(ctx: &Context<'_>, scope: Option<PlaylistScope>) -> Result<PlaylistOwner, Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    Ok(PlaylistOwner::new(
+        scope.unwrap_or_default(),
+        ctx.author().id,
+        guild_id,
+    ))
+}
+
+/// サーバー共有のプレイリストを変更できるのは DJ のみ。
+async fn can_edit(ctx: &Context<'_>, scope: Option<PlaylistScope>) -> Result<bool, Error> {
+    match scope.unwrap_or_default() {
+        PlaylistScope::Personal => Ok(true),
+        PlaylistScope::Guild => require(ctx, MusicAction::Control).await,
+    }
+}
+
+fn track_label(t: &StoredTrack) -> String {
+    t.title.clone().unwrap_or_else(|| t.url.clone())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 今のキューか再生履歴をプレイリストとして保存します（同名は上書き）
+pub async fn save(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "保存する内容 (既定: キュー)"] source: Option<PlaylistSource>,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let name = normalize_name(&name)?;
+    if !can_edit(&ctx, scope).await? {
+        return Ok(());
+    }
+    let tracks: Vec<StoredTrack> = match source.unwrap_or_default() {
+        PlaylistSource::Queue => {
+            let current = ctx
+                .data()
+                .lavalink_playing
+                .get(&guild_id)
+                .map(|e| StoredTrack::from(e.value()));
+            let queued = ctx
+                .data()
+                .queues
+                .get(&guild_id)
+                .map(|q| q.play_order().into_iter().map(StoredTrack::from).collect())
+                .unwrap_or_default();
+            current.into_iter().chain(queued).collect()
+        }
+        PlaylistSource::History => ctx
+            .data()
+            .history
+            .get(&guild_id)
+            .map(|h| h.iter().map(StoredTrack::from).collect())
+            .unwrap_or_default(),
+    };
+    if tracks.is_empty() {
+        ctx.say("📭 保存する曲がありません").await?;
+        return Ok(());
+    }
+    let total = tracks.len().min(MAX_PLAYLIST_TRACKS);
+    let mut playlist = SavedPlaylist::new(name.clone(), ctx.author().id, tracks);
+    playlist.tracks.truncate(MAX_PLAYLIST_TRACKS);
+
+    let replaced = ctx
+        .data()
+        .playlists
+        .save(owner(&ctx, scope)?, playlist)
+        .await?;
+    let verb = if replaced {
+        "上書き保存"
+    } else {
+        "保存"
+    };
+    ctx.say(format!(
+        "💾 プレイリスト「{name}」に {total} 曲を{verb}しました"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストをキューに追加します
+pub async fn load(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.defer().await?;
+    let playlist = ctx
+        .data()
+        .playlists
+        .get(owner(&ctx, scope)?, name.trim())
+        .await?;
+    let reqs = playlist.requests(ctx.author().id);
+    if reqs.is_empty() {
+        ctx.say("📭 プレイリストは空です").await?;
+        return Ok(());
+    }
+    let preview = reqs.first().cloned();
+
+    let limits = ctx.data().settings.get(guild_id).queue_limits();
+    let outcome = ctx
+        .data()
+        .queues
+        .entry(guild_id)
+        .or_default()
+        .extend_checked(reqs, &limits);
+    if outcome.added == 0 {
+        if let Some(reason) = &outcome.reason {
+            let embed = queue_error_embed(preview.as_ref(), reason);
+            ctx.send(CreateReply::default().embed(embed)).await?;
+        }
+        return Ok(());
+    }
+    ctx.data().store.mark_dirty(guild_id);
+    tracing::info!(
+        guild = %guild_id,
+        playlist = %playlist.name,
+        added = outcome.added,
+        skipped = outcome.skipped,
+        "saved playlist loaded"
+    );
+
+    let mut note = format!(
+        "プレイリスト「{}」から {} 件をキューに追加しました。",
+        playlist.name, outcome.added
+    );
+    if let Some(skipped) = batch_skip_note(&outcome) {
+        note.push('\n');
+        note.push_str(&skipped);
+    }
+    let embed = match try_autostart_from_queue(&ctx, guild_id).await {
+        Some(started) => track_embed(
+            "🎶 プレイリストを読み込んで再生開始しました",
+            Some(&started),
+            Some(note),
+            SUCCESS,
+        ),
+        None => track_embed(
+            "📃 プレイリストを読み込みました",
+            preview.as_ref(),
+            Some(note),
+            ACCENT,
+        ),
+    };
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 保存したプレイリストの一覧を表示します
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let playlists = ctx.data().playlists.list(owner(&ctx, scope)?).await?;
+    if playlists.is_empty() {
+        ctx.say("📭 保存したプレイリストはありません").await?;
+        return Ok(());
+    }
+    let header = format!(
+        "📚 **{}のプレイリスト** ({}/{MAX_PLAYLISTS})",
+        scope.unwrap_or_default().name(),
+        playlists.len()
+    );
+    let lines: Vec<String> = playlists
+        .iter()
+        .map(|p| format!("- {} ({} 曲)", p.name, p.tracks.len()))
+        .collect();
+    let pages: Vec<String> = lines
+        .chunks(20)
+        .map(|chunk| format!("{header}\n{}", chunk.join("\n")))
+        .collect();
+    let slices: Vec<&str> = pages.iter().map(String::as_str).collect();
+    poise::builtins::paginate(ctx, &slices).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストの曲を表示します
+pub async fn show(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let playlist = ctx
+        .data()
+        .playlists
+        .get(owner(&ctx, scope)?, name.trim())
+        .await?;
+    if playlist.tracks.is_empty() {
+        ctx.say(format!("📭 プレイリスト「{}」は空です", playlist.name))
+            .await?;
+        return Ok(());
+    }
+    let lines: Vec<String> = playlist
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(i, t)| format!("{}. {}", i + 1, track_label(t)))
+        .collect();
+    let pages: Vec<String> = lines
+        .chunks(15)
+        .map(|chunk| {
+            format!(
+                "📃 **{}** ({} 曲)\n{}",
+                playlist.name,
+                playlist.tracks.len(),
+                chunk.join("\n")
+            )
+        })
+        .collect();
+    let slices: Vec<&str> = pages.iter().map(String::as_str).collect();
+    poise::builtins::paginate(ctx, &slices).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストに曲を追加します（無ければ作成）
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "YouTube URL または検索語"] query: String,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let name = normalize_name(&name)?;
+    if !can_edit(&ctx, scope).await? {
+        return Ok(());
+    }
+    ctx.defer().await?;
+    let req = TrackRequest::from_url(query, ctx.author().id).await?;
+    let track = StoredTrack::from(&req);
+    let label = track_label(&track);
+    let author = ctx.author().id;
+    let count = ctx
+        .data()
+        .playlists
+        .update(owner(&ctx, scope)?, |playlists| {
+            if !playlists.contains_key(&name) && playlists.len() >= MAX_PLAYLISTS {
+                return Err(Error::from(format!(
+                    "保存できるプレイリストは {MAX_PLAYLISTS} 件までです"
+                )));
+            }
+            let playlist = playlists
+                .entry(name.clone())
+                .or_insert_with(|| SavedPlaylist::new(name.clone(), author, Vec::new()));
+            if playlist.tracks.len() >= MAX_PLAYLIST_TRACKS {
+                return Err(Error::from(format!(
+                    "1 つのプレイリストに入れられるのは {MAX_PLAYLIST_TRACKS} 曲までです"
+                )));
+            }
+            playlist.tracks.push(track);
+            playlist.updated_at = chrono::Utc::now().to_rfc3339();
+            Ok(playlist.tracks.len())
+        })
+        .await?;
+    ctx.say(format!(
+        "➕ プレイリスト「{name}」に **{label}** を追加しました（{count} 曲）"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストから曲を削除します
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "曲の位置 (1〜)"] index: usize,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    if !can_edit(&ctx, scope).await? {
+        return Ok(());
+    }
+    let name = name.trim().to_string();
+    let (removed, emptied) = ctx
+        .data()
+        .playlists
+        .update(owner(&ctx, scope)?, |playlists| {
+            let playlist = playlists.get_mut(&name).ok_or_else(|| {
+                Error::from(format!("プレイリスト「{name}」は見つかりませんでした"))
+            })?;
+            let len = playlist.tracks.len();
+            if index == 0 || index > len {
+                return Err(Error::from(format!("有効な範囲は 1〜{len} です")));
+            }
+            playlist.updated_at = chrono::Utc::now().to_rfc3339();
+            let removed = playlist.tracks.remove(index - 1);
+            // 最後の曲を消したら空のプレイリストは残さない
+            let emptied = playlist.tracks.is_empty();
+            if emptied {
+                playlists.remove(&name);
+            }
+            Ok((removed, emptied))
+        })
+        .await?;
+    let mut message = format!(
+        "🗑️ プレイリスト「{name}」から **{}** を削除しました",
+        track_label(&removed)
+    );
+    if emptied {
+        message.push_str("\n曲が無くなったのでプレイリストも削除しました");
+    }
+    ctx.say(message).await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストを削除します
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    if !can_edit(&ctx, scope).await? {
+        return Ok(());
+    }
+    let name = name.trim().to_string();
+    ctx.data()
+        .playlists
+        .update(owner(&ctx, scope)?, |playlists| {
+            playlists
+                .remove(&name)
+                .map(|_| ())
+                .ok_or_else(|| Error::from(format!("プレイリスト「{name}」は見つかりませんでした")))
+        })
+        .await?;
+    ctx.say(format!("🗑️ プレイリスト「{name}」を削除しました"))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストを JSON か M3U ファイルとして書き出します
+pub async fn export(
+    ctx: Context<'_>,
+    #[description = "プレイリスト名"] name: String,
+    #[description = "形式 (既定: JSON)"] format: Option<ExportFormat>,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    let playlist = ctx
+        .data()
+        .playlists
+        .get(owner(&ctx, scope)?, name.trim())
+        .await?;
+    let (bytes, ext) = match format.unwrap_or_default() {
+        ExportFormat::Json => (playlist.to_json()?, "json"),
+        ExportFormat::M3u => (playlist.to_m3u().into_bytes(), "m3u"),
+    };
+    // ファイル名に使えない文字は置き換える
+    let stem: String = playlist
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let attachment = CreateAttachment::bytes(bytes, format!("{stem}.{ext}"));
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "📤 プレイリスト「{}」（{} 曲）",
+                playlist.name,
+                playlist.tracks.len()
+            ))
+            .attachment(attachment),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// JSON か M3U ファイルからプレイリストを取り込みます（同名は上書き）
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "JSON または M3U ファイル"] file: Attachment,
+    #[description = "プレイリスト名 (既定: ファイル名)"] name: Option<String>,
+    #[description = "保存先 (既定: 自分用)"] scope: Option<PlaylistScope>,
+) -> Result<(), Error> {
+    if !can_edit(&ctx, scope).await? {
+        return Ok(());
+    }
+    if file.size > MAX_IMPORT_BYTES {
+        ctx.say("❌ ファイルが大きすぎます（1 MB まで）").await?;
+        return Ok(());
+    }
+    let name = match name {
+        Some(name) => normalize_name(&name)?,
+        None => normalize_name(
+            file.filename
+                .rsplit_once('.')
+                .map_or(file.filename.as_str(), |(stem, _)| stem),
+        )?,
+    };
+    ctx.defer().await?;
+    let bytes = file.download().await?;
+    let playlist = match SavedPlaylist::parse(&bytes, name.clone(), ctx.author().id) {
+        Ok(playlist) => playlist,
+        Err(err) => {
+            ctx.say(format!("❌ 読み込めませんでした: {err}")).await?;
+            return Ok(());
+        }
+    };
+    let total = playlist.tracks.len();
+    let replaced = ctx
+        .data()
+        .playlists
+        .save(owner(&ctx, scope)?, playlist)
+        .await?;
+    let verb = if replaced {
+        "上書き保存"
+    } else {
+        "保存"
+    };
+    ctx.say(format!(
+        "📥 {total} 曲をプレイリスト「{name}」として{verb}しました"
+    ))
+    .await?;
+    Ok(())
+}
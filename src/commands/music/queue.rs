@@ -4,10 +4,10 @@ use crate::{
     util::{
         alias::Context,
         lavalink_player::{current_play_mode, play_next_from_queue_lavalink},
-        music_ui::track_embed,
-        playlist,
+        music_ui::{batch_skip_note, queue_error_embed, track_embed},
+        playlist::{self, PlaylistContext, PlaylistOptions, PlaylistRange},
         queue::MusicQueue,
-        track::{TrackMetadata, TrackRequest},
+        track::{TrackMetadata, TrackRequest, metadata_lookup_key, youtube_video_id},
     },
 };
 use dashmap::DashMap;
@@ -30,12 +30,9 @@ use std::{
     },
     time::Duration,
 };
-use url::Url;
 
 const PAGE_SIZE: usize = 10;
-const MAX_PLAYLIST_ITEMS: usize = 50;
 const PREFETCH_METADATA_MAX_ITEMS: usize = 50;
-const UI_TIMEOUT: Duration = Duration::from_secs(300);
 const ACCENT: Colour = Colour::new(0x5865F2);
 const SUCCESS: Colour = Colour::new(0x2ECC71);
 const DANGER: Colour = Colour::new(0xE74C3C);
@@ -59,38 +56,6 @@ fn format_mmss(dur: Option<Duration>) -> String {
         .unwrap_or_else(|| "--:--".to_string())
 }
 
-fn youtube_video_id(raw: &str) -> Option<String> {
-    let url = Url::parse(raw).ok()?;
-    let host = url.host_str().unwrap_or_default();
-    if host.contains("youtube.com") || host.contains("m.youtube.com") {
-        let id = url
-            .query_pairs()
-            .find_map(|(k, v)| (k == "v").then_some(v))?;
-        let id = id.trim();
-        if id.is_empty() {
-            return None;
-        }
-        return Some(id.to_string());
-    }
-    if host.contains("youtu.be") {
-        let seg = url.path_segments().and_then(|mut s| s.next())?;
-        let seg = seg.trim();
-        if seg.is_empty() {
-            return None;
-        }
-        return Some(seg.to_string());
-    }
-    None
-}
-
-fn normalize_youtube_key(url: &str) -> Option<String> {
-    youtube_video_id(url).map(|id| format!("yt:{id}"))
-}
-
-fn metadata_lookup_key(url: &str) -> String {
-    normalize_youtube_key(url).unwrap_or_else(|| format!("url:{url}"))
-}
-
 fn short_url(tr: &TrackRequest) -> String {
     let raw_url = tr.meta.source_url.as_deref().unwrap_or(&tr.url);
     if let Some(id) = youtube_video_id(raw_url) {
@@ -348,10 +313,11 @@ async fn ensure_page_metadata(
     let total = snapshot.len();
     let pages = total_pages(total);
     let page = page.min(pages.saturating_sub(1));
-    let (start, end) = page_slice_bounds(page, total);
+    let (start, _end) = page_slice_bounds(page, total);
 
     let urls_to_fetch = snapshot
-        .iter()
+        .play_order()
+        .into_iter()
         .skip(start)
         .take(PAGE_SIZE)
         .filter(|tr| tr.meta.title.as_ref().is_none_or(|t| t.trim().is_empty()))
@@ -369,14 +335,10 @@ async fn ensure_page_metadata(
         Err(_) => return Ok(()),
     };
 
+    // 取得中に並びが変わることがあるので、位置ではなく URL で当てはめる
     let mut entry = queues.entry(guild_id).or_default();
     let queue = entry.value_mut();
-    let total = queue.len();
-    let end = end.min(total);
-    for idx in start..end {
-        let Some(tr) = queue.queue.get_mut(idx) else {
-            continue;
-        };
+    for tr in queue.queue.iter_mut() {
         if tr.meta.title.as_ref().is_some_and(|t| !t.trim().is_empty()) {
             continue;
         }
@@ -390,22 +352,25 @@ async fn ensure_page_metadata(
     Ok(())
 }
 
-fn pages_from_urls(urls: &[String], title: &str) -> Vec<String> {
-    let pages = total_pages(urls.len());
+fn pages_from_lines(lines: &[String], title: &str) -> Vec<String> {
+    let pages = total_pages(lines.len());
     let mut out = Vec::with_capacity(pages);
     for p in 0..pages {
-        let (start, end) = page_slice_bounds(p, urls.len());
+        let (start, end) = page_slice_bounds(p, lines.len());
         let mut s = format!("Page {}/{}\n\n", p + 1, pages);
-        for (i, url) in urls[start..end].iter().enumerate() {
+        for (i, line) in lines[start..end].iter().enumerate() {
             let idx = start + i + 1;
-            s.push_str(&format!("{idx}. {url}\n"));
+            s.push_str(&format!("{idx}. {line}\n"));
         }
         out.push(format!("{title}\n\n{s}"));
     }
     out
 }
 
-async fn try_autostart_from_queue(ctx: &Context<'_>, guild_id: GuildId) -> Option<TrackRequest> {
+pub(crate) async fn try_autostart_from_queue(
+    ctx: &Context<'_>,
+    guild_id: GuildId,
+) -> Option<TrackRequest> {
     let lavalink = ctx.data().lavalink.clone()?;
     if current_play_mode(&lavalink, guild_id).await != PlayMode::Stop {
         return None;
@@ -449,11 +414,83 @@ async fn try_autostart_from_queue(ctx: &Context<'_>, guild_id: GuildId) -> Optio
     }
 }
 
+/// 展開したプレイリストをキューに追加し、停止中なら再生を始める。
+async fn enqueue_expanded(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    reqs: Vec<TrackRequest>,
+) -> Result<(), Error> {
+    tracing::info!(guild = %guild_id, items = reqs.len(), "playlist expanded (queue)");
+    // 外部サービスから展開した曲はまだ URL が無いので曲名で一覧にする
+    let labels = reqs
+        .iter()
+        .map(|r| match (&r.meta.artist, &r.meta.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (None, Some(title)) => title.clone(),
+            _ => r.url.clone(),
+        })
+        .collect::<Vec<_>>();
+    let queues = ctx.data().queues.clone();
+    let limits = ctx.data().settings.get(guild_id).queue_limits();
+    let outcome = queues
+        .entry(guild_id)
+        .or_default()
+        .extend_checked(reqs, &limits);
+    if outcome.added == 0 {
+        if let Some(reason) = &outcome.reason {
+            let embed = queue_error_embed(None, reason);
+            ctx.send(CreateReply::default().embed(embed)).await?;
+        }
+        return Ok(());
+    }
+    let total = outcome.added;
+    ctx.data().store.mark_dirty(guild_id);
+    tracing::info!(
+        guild = %guild_id,
+        added = total,
+        skipped = outcome.skipped,
+        "playlist enqueued"
+    );
+    let mut note = format!("{total} 件をキューに追加しました。");
+    if let Some(skipped) = batch_skip_note(&outcome) {
+        note.push('\n');
+        note.push_str(&skipped);
+    }
+    let started = try_autostart_from_queue(&ctx, guild_id).await;
+    if let Some(req) = started {
+        let embed = track_embed(
+            "🎶 プレイリストを追加して再生開始しました",
+            Some(&req),
+            Some(note),
+            SUCCESS,
+        );
+        ctx.send(CreateReply::default().embed(embed)).await?;
+    } else {
+        let preview = queues.get(&guild_id).and_then(|q| q.iter().next().cloned());
+        let embed = track_embed(
+            "📃 プレイリストをキューに追加しました",
+            preview.as_ref(),
+            Some(note),
+            ACCENT,
+        );
+        ctx.send(CreateReply::default().embed(embed)).await?;
+    }
+
+    let pages = pages_from_lines(&labels, "追加したトラック");
+    let slices: Vec<&str> = pages.iter().map(String::as_str).collect();
+    poise::builtins::paginate(ctx, &slices).await?;
+    Ok(())
+}
+
 #[poise::command(slash_command, guild_only)]
 pub async fn queue(
     ctx: Context<'_>,
+    #[description = "プレイリストから追加する範囲 (例: 20-60, 20-)"] range: Option<PlaylistRange>,
+    #[description = "プレイリストをシャッフルして追加"]
+    #[flag]
+    shuffle: bool,
     #[rest]
-    #[description = "YouTube URL または検索語 (指定するとキューに追加)"]
+    #[description = "URL (YouTube / Spotify / Apple Music / Deezer) または検索語 (指定するとキューに追加)"]
     query: Option<String>,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
@@ -468,67 +505,57 @@ pub async fn queue(
     );
 
     if let Some(q) = query {
-        if playlist::is_youtube_playlist_url(&q) {
-            tracing::info!(guild = %guild_id, "expanding youtube playlist (queue)");
+        let limits = ctx.data().settings.get(guild_id).queue_limits();
+        let mut resolved = None;
+        if let Some(provider) = playlist::find_provider(&q) {
+            tracing::info!(guild = %guild_id, provider = provider.name(), "expanding playlist source (queue)");
             ctx.defer().await?;
-            match playlist::expand_youtube_playlist(&q, MAX_PLAYLIST_ITEMS).await {
-                Ok(urls) => {
-                    tracing::info!(guild = %guild_id, items = urls.len(), "playlist expanded (queue)");
-                    let reqs = urls
-                        .iter()
-                        .cloned()
-                        .map(|u| TrackRequest::new(u, ctx.author().id))
-                        .collect::<Vec<_>>();
-                    let total = reqs.len();
-                    {
-                        let mut guard = queues.entry(guild_id).or_default();
-                        for r in reqs {
-                            guard.push_back(r);
-                        }
-                    }
-                    tracing::info!(guild = %guild_id, added = total, "playlist enqueued");
-                    let started = try_autostart_from_queue(&ctx, guild_id).await;
-                    if let Some(req) = started {
-                        let embed = track_embed(
-                            "🎶 プレイリストを追加して再生開始しました",
-                            Some(&req),
-                            Some(format!("{total} 件をキューに追加しました。")),
-                            SUCCESS,
-                        );
-                        ctx.send(CreateReply::default().embed(embed)).await?;
-                    } else {
-                        let preview = queues.get(&guild_id).and_then(|q| q.iter().next().cloned());
-                        let embed = track_embed(
-                            "📃 プレイリストをキューに追加しました",
-                            preview.as_ref(),
-                            Some(format!("{total} 件をキューに追加しました。")),
-                            ACCENT,
-                        );
-                        ctx.send(CreateReply::default().embed(embed)).await?;
-                    }
-
-                    let pages = pages_from_urls(&urls, "追加したトラック(URL一覧)");
-                    let slices: Vec<&str> = pages.iter().map(String::as_str).collect();
-                    poise::builtins::paginate(ctx, &slices).await?;
+            let cx = PlaylistContext {
+                lavalink: lavalink.as_deref(),
+                guild_id,
+                requested_by: owner_id,
+            };
+            let cap = ctx.data().settings.get(guild_id).playlist_limit;
+            let options = PlaylistOptions { range, shuffle };
+            match playlist::expand_with_options(provider, &cx, &q, options, cap).await {
+                Ok(mut expanded) if expanded.requests.len() == 1 => {
+                    resolved = Some(expanded.requests.remove(0))
                 }
+                // 展開できなかった URL は通常の 1 曲として扱う
+                Ok(expanded) if expanded.requests.is_empty() => {}
+                Ok(expanded) => return enqueue_expanded(ctx, guild_id, expanded.requests).await,
                 Err(e) => {
                     let embed = track_embed(
-                        "❌ プレイリスト展開に失敗しました",
+                        &format!("❌ {} を読み込めませんでした", provider.name()),
                         None,
                         Some(e.to_string()),
                         DANGER,
                     );
                     ctx.send(CreateReply::default().embed(embed)).await?;
+                    return Ok(());
                 }
             }
-            return Ok(());
         }
 
         ctx.defer().await?;
         tracing::info!(guild = %guild_id, "adding single track to queue");
-        match TrackRequest::from_url(q, ctx.author().id).await {
+        let resolved = match resolved {
+            Some(req) => Ok(req),
+            None => TrackRequest::from_url(q, ctx.author().id).await,
+        };
+        match resolved {
             Ok(req) => {
-                queues.entry(guild_id).or_default().push_back(req.clone());
+                let pushed = queues
+                    .entry(guild_id)
+                    .or_default()
+                    .try_push_back(req.clone(), &limits);
+                if let Err(err) = pushed {
+                    tracing::info!(guild = %guild_id, url = %req.url, reason = %err, "enqueue rejected");
+                    let embed = queue_error_embed(Some(&req), &err);
+                    ctx.send(CreateReply::default().embed(embed)).await?;
+                    return Ok(());
+                }
+                ctx.data().store.mark_dirty(guild_id);
                 tracing::info!(guild = %guild_id, url = %req.url, "enqueued track");
                 if let Some(started) = try_autostart_from_queue(&ctx, guild_id).await {
                     let embed = track_embed(
@@ -554,7 +581,7 @@ pub async fn queue(
 
     let mut list = queues
         .get(&guild_id)
-        .map(|q| q.iter().cloned().collect::<Vec<_>>())
+        .map(|q| q.play_order().into_iter().cloned().collect::<Vec<_>>())
         .unwrap_or_default();
 
     if list.is_empty() {
@@ -565,6 +592,7 @@ pub async fn queue(
     // Initial response time limit is 3s; defer and then build UI.
     ctx.defer().await?;
 
+    let ui_timeout = ctx.data().settings.get(guild_id).ui_timeout();
     let mut page = 0usize;
 
     let mut pages = total_pages(list.len());
@@ -611,7 +639,7 @@ pub async fn queue(
             }
             let list = queues
                 .get(&guild_id)
-                .map(|q| q.iter().cloned().collect::<Vec<_>>())
+                .map(|q| q.play_order().into_iter().cloned().collect::<Vec<_>>())
                 .unwrap_or_default();
             if list.is_empty() {
                 return;
@@ -634,7 +662,7 @@ pub async fn queue(
         let interaction: Option<ComponentInteraction> =
             poise::serenity_prelude::ComponentInteractionCollector::new(ctx.serenity_context())
                 .message_id(msg.id)
-                .timeout(UI_TIMEOUT)
+                .timeout(ui_timeout)
                 .await;
 
         let Some(interaction) = interaction else {
@@ -703,7 +731,7 @@ pub async fn queue(
 
         list = queues
             .get(&guild_id)
-            .map(|q| q.iter().cloned().collect::<Vec<_>>())
+            .map(|q| q.play_order().into_iter().cloned().collect::<Vec<_>>())
             .unwrap_or_default();
         pages = total_pages(list.len());
         if page >= pages {
@@ -750,7 +778,7 @@ pub async fn queue(
             }
             let list = queues2
                 .get(&guild_id)
-                .map(|q| q.iter().cloned().collect::<Vec<_>>())
+                .map(|q| q.play_order().into_iter().cloned().collect::<Vec<_>>())
                 .unwrap_or_default();
             if list.is_empty() {
                 return;
@@ -1,28 +1,118 @@
 // src/commands/music/remove.rs
-use crate::{Error, util::alias::Context};
+use poise::serenity_prelude::{Mentionable, User, UserId};
+
+use crate::{
+    Error,
+    util::{
+        alias::Context,
+        permissions::{MusicAction, require},
+        track::TrackRequest,
+    },
+};
+
+/// `3` または `3-7` を 0 始まりの範囲に変換する。
+fn parse_range(s: &str) -> Option<std::ops::Range<usize>> {
+    let (from, to) = match s.split_once('-') {
+        Some((from, to)) => (from.trim().parse().ok()?, to.trim().parse().ok()?),
+        None => {
+            let n = s.trim().parse().ok()?;
+            (n, n)
+        }
+    };
+    (from >= 1 && from <= to).then(|| from - 1..to)
+}
+
+/// 対象がすべて同じ人のリクエストならその人を返す（本人による削除の判定用）。
+fn sole_requester<'a>(tracks: impl IntoIterator<Item = &'a TrackRequest>) -> Option<UserId> {
+    let mut users = tracks.into_iter().map(|t| t.requested_by);
+    let first = users.next()?;
+    users.all(|u| u == first).then_some(first)
+}
 
 #[poise::command(slash_command, prefix_command, guild_only)]
+/// キューから曲を削除します（位置・範囲、またはリクエストした人で指定）
 pub async fn remove(
     ctx: Context<'_>,
-    #[description = "キューの位置 (1〜)"] index: usize,
+    #[description = "キューの位置 (1〜) または範囲 (例: 3-7)"] position: Option<String>,
+    #[description = "この人がリクエストした曲をすべて削除"] user: Option<User>,
 ) -> Result<(), Error> {
     let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
-    let mut entry = ctx.data().queues.entry(gid).or_default();
-    let queue = entry.value_mut();
 
-    if index == 0 || index > queue.len() {
-        ctx.reply(format!("❌ 有効な範囲は 1〜{} です", queue.len()))
+    if let Some(user) = user {
+        let requester = Some(user.id);
+        if !require(&ctx, MusicAction::Remove { requester }).await? {
+            return Ok(());
+        }
+        let removed = ctx
+            .data()
+            .queues
+            .get_mut(&gid)
+            .map(|mut q| q.remove_by_user(user.id))
+            .unwrap_or_default();
+        if removed.is_empty() {
+            ctx.reply(format!(
+                "📭 {} がリクエストした曲はキューにありません",
+                user.mention()
+            ))
             .await?;
+            return Ok(());
+        }
+        ctx.data().store.mark_dirty(gid);
+        ctx.reply(format!(
+            "🗑️ {} がリクエストした {} 曲を削除しました",
+            user.mention(),
+            removed.len()
+        ))
+        .await?;
         return Ok(());
     }
 
-    // 0-based に換算して削除
-    if let Some(tr) = queue.remove_at(index - 1) {
-        let title = tr.meta.title.as_deref().unwrap_or("Unknown Title");
-        ctx.reply(format!("🗑️ キューから削除しました: **{}**", title))
+    let Some(range) = position.as_deref().and_then(parse_range) else {
+        ctx.reply("❌ 位置（例: `3`）か範囲（例: `3-7`）、または `user` を指定してください")
             .await?;
-    } else {
-        ctx.reply("❌ 削除に失敗しました").await?;
+        return Ok(());
+    };
+
+    // 番号は `/queue` に表示される再生順
+    let (len, requester) = match ctx.data().queues.get(&gid) {
+        Some(q) => {
+            let targets = q.play_order();
+            let requester = targets
+                .get(range.start..range.end.min(targets.len()))
+                .and_then(|t| sole_requester(t.iter().copied()));
+            (q.len(), requester)
+        }
+        None => (0, None),
+    };
+    if range.end > len {
+        ctx.reply(format!("❌ 有効な範囲は 1〜{len} です")).await?;
+        return Ok(());
+    }
+    if !require(&ctx, MusicAction::Remove { requester }).await? {
+        return Ok(());
+    }
+
+    let removed = ctx
+        .data()
+        .queues
+        .get_mut(&gid)
+        .map(|mut q| q.remove_range(range))
+        .unwrap_or_default();
+    match removed.as_slice() {
+        [] => {
+            ctx.reply("❌ 削除に失敗しました").await?;
+        }
+        [tr] => {
+            ctx.data().store.mark_dirty(gid);
+            let title = tr.meta.title.as_deref().unwrap_or("Unknown Title");
+            ctx.reply(format!("🗑️ キューから削除しました: **{}**", title))
+                .await?;
+        }
+        many => {
+            ctx.data().store.mark_dirty(gid);
+            ctx.reply(format!("🗑️ キューから {} 曲を削除しました", many.len()))
+                .await?;
+        }
     }
     Ok(())
 }
@@ -1,5 +1,6 @@
 use crate::util::{
     alias::{Context, Error},
+    permissions::{MusicAction, require},
     repeat::RepeatMode,
 };
 
@@ -9,8 +10,13 @@ pub async fn repeat(
     #[description = "Off / Track / Queue"] mode: RepeatMode,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
     let mut entry = ctx.data().queues.entry(guild_id).or_default();
     entry.value_mut().set_repeat_mode(mode); // ← 変更はここだけ
+    drop(entry);
+    ctx.data().store.mark_dirty(guild_id);
     ctx.say(format!("🔁 リピートモードを **{mode:?}** に設定しました"))
         .await?;
     Ok(())
@@ -1,14 +1,34 @@
-use crate::{Error, util::alias::Context};
-use lavalink_rs::model::{
-    search::SearchEngines,
-    track::{Track as LavalinkTrack, TrackData, TrackLoadData},
+use crate::{
+    Error,
+    commands::music::{join::_join, queue::try_autostart_from_queue},
+    util::{
+        alias::Context,
+        lavalink_player::{apply_track_metadata, current_play_mode, play_track_req_lavalink},
+        music_ui::{track_embed, truncate_chars},
+        permissions::{MusicAction, check_interaction},
+        player::ManualTransitionGuard,
+        search_source::{self, SearchSource, autocomplete_source},
+        track::TrackRequest,
+    },
 };
-use poise::builtins::paginate;
+use lavalink_rs::model::track::TrackData;
+use poise::CreateReply;
+use poise::serenity_prelude::{
+    ButtonStyle, Colour, ComponentInteraction, ComponentInteractionCollector,
+    ComponentInteractionDataKind, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, GuildId,
+};
+use songbird::tracks::PlayMode;
 use std::time::Duration;
 
 const PAGE_SIZE: usize = 5;
 const MAX_RESULTS: usize = 50;
 
+const ACCENT: Colour = Colour::new(0x5865F2);
+const SUCCESS: Colour = Colour::new(0x2ECC71);
+const DANGER: Colour = Colour::new(0xE74C3C);
+
 fn to_duration(length_ms: u64, is_stream: bool) -> Option<Duration> {
     if is_stream || length_ms == 0 {
         None
@@ -32,26 +52,206 @@ fn track_url(track: &TrackData) -> String {
     "-".into()
 }
 
-fn first_tracks(load: LavalinkTrack) -> Result<Vec<TrackData>, Error> {
-    match load.data {
-        Some(TrackLoadData::Track(track)) => Ok(vec![track]),
-        Some(TrackLoadData::Search(tracks)) => Ok(tracks),
-        Some(TrackLoadData::Playlist(playlist)) => Ok(playlist.tracks),
-        Some(TrackLoadData::Error(err)) => Err(Error::from(format!(
-            "Lavalink search failed: {}",
-            err.message
-        ))),
-        None => Ok(Vec::new()),
+fn result_title(req: &TrackRequest) -> &str {
+    req.meta
+        .title
+        .as_deref()
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or("Unknown")
+}
+
+/// 検索結果に対する操作ボタン。
+#[derive(Copy, Clone, Debug)]
+enum SearchAction {
+    PlayNow,
+    Enqueue,
+    InsertNext,
+}
+
+fn total_pages(total: usize) -> usize {
+    total.div_ceil(PAGE_SIZE).max(1)
+}
+
+/// 検索 UI の状態。`selected` は結果全体での位置（0 始まり）。
+struct SearchView<'a> {
+    query: &'a str,
+    source: SearchSource,
+    results: &'a [TrackRequest],
+    page: usize,
+    selected: usize,
+}
+
+impl SearchView<'_> {
+    fn page_range(&self) -> std::ops::Range<usize> {
+        let start = self.page * PAGE_SIZE;
+        start..(start + PAGE_SIZE).min(self.results.len())
     }
+
+    /// 選択中の曲を `track_embed` でプレビューし、説明欄にページ内の一覧を出す。
+    fn embed(&self, status: Option<&str>) -> CreateEmbed {
+        let mut desc = String::new();
+        for idx in self.page_range() {
+            let req = &self.results[idx];
+            let marker = if idx == self.selected { "▶" } else { "　" };
+            desc.push_str(&format!(
+                "{marker} **{}.** {} `[{}]`\n",
+                idx + 1,
+                truncate_chars(result_title(req), 70),
+                format_duration(req.meta.duration)
+            ));
+        }
+        if let Some(status) = status {
+            desc.push('\n');
+            desc.push_str(status);
+        }
+        let title = format!(
+            "🔎 『{}』の検索結果 - {} ({}/{})",
+            truncate_chars(self.query, 60),
+            self.source.label(),
+            self.page + 1,
+            total_pages(self.results.len())
+        );
+        track_embed(&title, self.results.get(self.selected), Some(desc), ACCENT)
+    }
+
+    fn components(&self) -> Vec<CreateActionRow> {
+        let options = self
+            .page_range()
+            .map(|idx| {
+                let req = &self.results[idx];
+                let label = truncate_chars(&format!("{}. {}", idx + 1, result_title(req)), 100);
+                let mut description = format_duration(req.meta.duration);
+                if let Some(artist) = req.meta.artist.as_deref() {
+                    description = truncate_chars(&format!("{artist} · {description}"), 100);
+                }
+                CreateSelectMenuOption::new(label, idx.to_string())
+                    .description(description)
+                    .default_selection(idx == self.selected)
+            })
+            .collect();
+        let menu = CreateSelectMenu::new("search_pick", CreateSelectMenuKind::String { options })
+            .placeholder("曲を選択")
+            .min_values(1)
+            .max_values(1);
+
+        let pages = total_pages(self.results.len());
+        vec![
+            CreateActionRow::SelectMenu(menu),
+            CreateActionRow::Buttons(vec![
+                CreateButton::new("search_play")
+                    .label("▶ 今すぐ再生")
+                    .style(ButtonStyle::Success),
+                CreateButton::new("search_queue")
+                    .label("📥 キューに追加")
+                    .style(ButtonStyle::Primary),
+                CreateButton::new("search_insert")
+                    .label("⏭ 次に再生")
+                    .style(ButtonStyle::Primary),
+            ]),
+            CreateActionRow::Buttons(vec![
+                CreateButton::new("search_prev")
+                    .label("<")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(self.page == 0),
+                CreateButton::new("search_next")
+                    .label(">")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(self.page + 1 >= pages),
+                CreateButton::new("search_close")
+                    .label("cancel")
+                    .style(ButtonStyle::Danger),
+            ]),
+        ]
+    }
+}
+
+async fn respond_ephemeral(ctx: &Context<'_>, interaction: &ComponentInteraction, content: &str) {
+    let builder = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::default()
+            .content(content)
+            .ephemeral(true),
+    );
+    let _ = interaction
+        .create_response(ctx.serenity_context(), builder)
+        .await;
+}
+
+/// 選んだ曲をすぐに再生する。再生中の曲を差し替える場合は再生操作の権限が要る。
+async fn play_now(
+    ctx: &Context<'_>,
+    guild_id: GuildId,
+    interaction: &ComponentInteraction,
+    req: TrackRequest,
+) -> Result<String, String> {
+    let lavalink = ctx
+        .data()
+        .lavalink
+        .clone()
+        .ok_or_else(|| "Lavalink client is not initialized".to_string())?;
+    if current_play_mode(&lavalink, guild_id).await != PlayMode::Stop {
+        check_interaction(ctx, guild_id, interaction, MusicAction::Control)?;
+    }
+    let limits = ctx.data().settings.get(guild_id).queue_limits();
+    limits.check_track(&req).map_err(|e| e.to_string())?;
+    _join(ctx, guild_id, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let _guard = ManualTransitionGuard::acquire(&ctx.data().transition_flags, guild_id);
+    let started = play_track_req_lavalink(
+        guild_id,
+        lavalink,
+        ctx.data().lavalink_playing.clone(),
+        ctx.data().history.clone(),
+        req,
+    )
+    .await
+    .map_err(|e| format!("再生開始に失敗しました: {e}"))?;
+    Ok(format!("🎶 **{}** を再生しました", result_title(&started)))
+}
+
+/// 選んだ曲をキューの末尾（`next` なら先頭）に入れ、停止中なら再生を始める。
+async fn enqueue(
+    ctx: &Context<'_>,
+    guild_id: GuildId,
+    interaction: &ComponentInteraction,
+    req: TrackRequest,
+    next: bool,
+) -> Result<String, String> {
+    if next {
+        check_interaction(ctx, guild_id, interaction, MusicAction::Insert)?;
+    }
+    let limits = ctx.data().settings.get(guild_id).queue_limits();
+    let pushed = {
+        let mut q = ctx.data().queues.entry(guild_id).or_default();
+        if next {
+            q.try_push_front(req.clone(), &limits).map(|_| 1)
+        } else {
+            q.try_push_back(req.clone(), &limits)
+        }
+    };
+    let position = pushed.map_err(|e| e.to_string())?;
+    ctx.data().store.mark_dirty(guild_id);
+    if let Some(started) = try_autostart_from_queue(ctx, guild_id).await {
+        return Ok(format!("🎶 **{}** を再生しました", result_title(&started)));
+    }
+    Ok(format!(
+        "📥 **{}** をキュー #{position} に追加しました",
+        result_title(&req)
+    ))
 }
 
 #[poise::command(slash_command, guild_only)]
+/// 曲を検索し、結果から選んで再生・キューに追加します
 pub async fn search(
     ctx: Context<'_>,
     #[rest]
     #[description = "検索キーワード"]
     query: String,
     #[description = "取得件数(1-50)"] count: Option<usize>,
+    #[description = "検索ソース（省略でサーバーの既定）"]
+    #[autocomplete = "autocomplete_source"]
+    source: Option<SearchSource>,
 ) -> Result<(), Error> {
     ctx.defer().await?;
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
@@ -60,16 +260,19 @@ pub async fn search(
         .lavalink
         .clone()
         .ok_or("Lavalink is not enabled in configuration")?;
+    let owner_id = ctx.author().id;
 
     let n = count.unwrap_or(5).clamp(1, MAX_RESULTS);
-    let identifier = SearchEngines::YouTube
-        .to_query(&query)
-        .map_err(|e| Error::from(format!("failed to build search query: {e}")))?;
-    let loaded = lavalink
-        .load_tracks(guild_id, &identifier)
-        .await
-        .map_err(|e| Error::from(format!("Lavalink search request failed: {e}")))?;
-    let mut tracks = first_tracks(loaded)?;
+    let source = source.unwrap_or_else(|| ctx.data().settings.get(guild_id).search_source);
+    if !source.is_available() {
+        ctx.say(format!(
+            "❌ {} の検索はこの Lavalink では使えません",
+            source.label()
+        ))
+        .await?;
+        return Ok(());
+    }
+    let mut tracks = search_source::search(&lavalink, guild_id, source, &query).await?;
     tracks.truncate(n);
 
     if tracks.is_empty() {
@@ -77,36 +280,133 @@ pub async fn search(
         return Ok(());
     }
 
-    let page_texts: Vec<String> = tracks
-        .chunks(PAGE_SIZE)
-        .enumerate()
-        .map(|(pi, chunk)| {
-            let mut txt = format!(
-                "🔎 『{}』の検索結果 ({}/{})\n\n",
-                query,
-                pi + 1,
-                (n + PAGE_SIZE - 1) / PAGE_SIZE
-            );
-            for (i, track) in chunk.iter().enumerate() {
-                let idx = pi * PAGE_SIZE + i + 1;
-                let title = if track.info.title.trim().is_empty() {
-                    "Unknown"
-                } else {
-                    track.info.title.as_str()
-                };
-                let url = track_url(track);
-                let dur = format_duration(to_duration(track.info.length, track.info.is_stream));
-                txt.push_str(&format!(
-                    "{}. **{}**\n▶️ {}\n⏱️ {}\n\n",
-                    idx, title, url, dur
-                ));
-            }
-            txt
+    let results: Vec<TrackRequest> = tracks
+        .iter()
+        .map(|track| {
+            let mut req = TrackRequest::new(track_url(track), owner_id);
+            apply_track_metadata(&mut req, track);
+            req.meta.duration = to_duration(track.info.length, track.info.is_stream);
+            req
         })
         .collect();
+    let mut view = SearchView {
+        query: &query,
+        source,
+        results: &results,
+        page: 0,
+        selected: 0,
+    };
+
+    let reply = CreateReply::default()
+        .embed(view.embed(None))
+        .components(view.components());
+    let handle = ctx.send(reply).await?;
+    let mut msg = handle.message().await?.into_owned();
+    let ui_timeout = ctx.data().settings.get(guild_id).ui_timeout();
+
+    loop {
+        let Some(interaction) = ComponentInteractionCollector::new(ctx.serenity_context())
+            .message_id(msg.id)
+            .timeout(ui_timeout)
+            .await
+        else {
+            break;
+        };
 
-    let page_slices: Vec<&str> = page_texts.iter().map(String::as_str).collect();
-    paginate(ctx, &page_slices).await?;
+        if interaction.user.id != owner_id {
+            respond_ephemeral(&ctx, &interaction, "この操作はコマンド実行者のみ可能です").await;
+            continue;
+        }
+
+        let selected = view.results[view.selected].clone();
+        let action = match interaction.data.custom_id.as_str() {
+            "search_close" => {
+                let builder = CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::default()
+                        .embeds(vec![view.embed(None)])
+                        .components(Vec::new()),
+                );
+                let _ = interaction
+                    .create_response(ctx.serenity_context(), builder)
+                    .await;
+                return Ok(());
+            }
+            "search_prev" => {
+                view.page = view.page.saturating_sub(1);
+                view.selected = view.page * PAGE_SIZE;
+                None
+            }
+            "search_next" => {
+                view.page = (view.page + 1).min(total_pages(results.len()) - 1);
+                view.selected = view.page * PAGE_SIZE;
+                None
+            }
+            "search_pick" => {
+                if let ComponentInteractionDataKind::StringSelect { values } =
+                    &interaction.data.kind
+                {
+                    if let Some(idx) = values.first().and_then(|v| v.parse::<usize>().ok()) {
+                        view.selected = idx.min(results.len() - 1);
+                    }
+                }
+                None
+            }
+            "search_play" => Some(SearchAction::PlayNow),
+            "search_queue" => Some(SearchAction::Enqueue),
+            "search_insert" => Some(SearchAction::InsertNext),
+            _ => None,
+        };
+
+        let Some(action) = action else {
+            let builder = CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::default()
+                    .embeds(vec![view.embed(None)])
+                    .components(view.components()),
+            );
+            let _ = interaction
+                .create_response(ctx.serenity_context(), builder)
+                .await;
+            continue;
+        };
+
+        // 接続・再生開始は 3 秒を超えることがあるので先に応答しておく
+        let _ = interaction
+            .create_response(
+                ctx.serenity_context(),
+                CreateInteractionResponse::Acknowledge,
+            )
+            .await;
+        let outcome = match action {
+            SearchAction::PlayNow => play_now(&ctx, guild_id, &interaction, selected.clone()).await,
+            SearchAction::Enqueue => {
+                enqueue(&ctx, guild_id, &interaction, selected.clone(), false).await
+            }
+            SearchAction::InsertNext => {
+                enqueue(&ctx, guild_id, &interaction, selected.clone(), true).await
+            }
+        };
+        let embed = match outcome {
+            Ok(status) => {
+                tracing::info!(guild = %guild_id, url = %selected.url, action = ?action, "search result used");
+                view.embed(Some(&status)).colour(SUCCESS)
+            }
+            Err(reason) => view.embed(Some(&format!("❌ {reason}"))).colour(DANGER),
+        };
+        let _ = msg
+            .edit(
+                ctx.serenity_context(),
+                EditMessage::new()
+                    .embed(embed)
+                    .components(view.components()),
+            )
+            .await;
+    }
 
+    let _ = msg
+        .edit(
+            ctx.serenity_context(),
+            EditMessage::new().components(Vec::new()),
+        )
+        .await;
     Ok(())
 }
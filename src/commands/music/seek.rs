@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use poise::CreateReply;
+use poise::serenity_prelude::Colour;
+
+use crate::util::{
+    alias::{Context, Error},
+    lavalink_player::seek_current_lavalink,
+    music_ui::{parse_timestamp, track_embed_at},
+    permissions::{MusicAction, require},
+    player::{SeekResult, SeekTarget},
+};
+
+async fn run_seek(ctx: Context<'_>, target: SeekTarget, title: &str) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let lavalink = ctx
+        .data()
+        .lavalink
+        .clone()
+        .ok_or("Lavalink is not enabled in configuration")?;
+    let playing = ctx.data().lavalink_playing.clone();
+
+    match seek_current_lavalink(&lavalink, guild_id, &playing, target).await? {
+        SeekResult::Moved(req, position, _) => {
+            let embed = track_embed_at(
+                title,
+                Some(&req),
+                None,
+                Colour::new(0x2ECC71),
+                Some(position),
+            );
+            ctx.send(CreateReply::default().embed(embed)).await?;
+        }
+        SeekResult::NotSeekable => {
+            ctx.say("この曲はシークできません").await?;
+        }
+        SeekResult::Missing => {
+            ctx.say("再生中の曲がありません").await?;
+        }
+    }
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 再生位置を指定した時刻へ移動します
+pub async fn seek(
+    ctx: Context<'_>,
+    #[description = "移動先 (例: 1:23, 1:02:03, 90)"] position: String,
+) -> Result<(), Error> {
+    ctx.defer().await?; // 3秒ルール
+    let Some(target) = parse_timestamp(&position) else {
+        ctx.say("❌ 時刻は `mm:ss` / `hh:mm:ss` / 秒数 で指定してください")
+            .await?;
+        return Ok(());
+    };
+    run_seek(
+        ctx,
+        SeekTarget::Absolute(target),
+        "🎯 再生位置を移動しました",
+    )
+    .await
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 指定秒数だけ早送りします
+pub async fn forward(
+    ctx: Context<'_>,
+    #[description = "進める秒数 (既定: 10)"]
+    #[min = 1]
+    secs: Option<u64>,
+) -> Result<(), Error> {
+    ctx.defer().await?; // 3秒ルール
+    let secs = secs.unwrap_or(10).max(1);
+    run_seek(
+        ctx,
+        SeekTarget::Forward(Duration::from_secs(secs)),
+        &format!("⏩ {secs}秒進めました"),
+    )
+    .await
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 指定秒数だけ巻き戻します
+pub async fn rewind(
+    ctx: Context<'_>,
+    #[description = "戻す秒数 (既定: 10)"]
+    #[min = 1]
+    secs: Option<u64>,
+) -> Result<(), Error> {
+    ctx.defer().await?; // 3秒ルール
+    let secs = secs.unwrap_or(10).max(1);
+    run_seek(
+        ctx,
+        SeekTarget::Backward(Duration::from_secs(secs)),
+        &format!("⏪ {secs}秒戻しました"),
+    )
+    .await
+}
@@ -1,4 +1,7 @@
-use crate::util::alias::{Context, Error};
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
 
 #[poise::command(slash_command, prefix_command, guild_only)]
 /// シャッフルモードを切り替えます
@@ -8,10 +11,21 @@ pub async fn shuffle(
 ) -> Result<(), Error> {
     ctx.defer().await?; // 3秒ルール
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
     let mut q = ctx.data().queues.entry(guild_id).or_default();
+    let was_fair = q.config.fair;
     q.value_mut().set_shuffle(option); // シャッフルモードの設定
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
     let status = if option { "ON" } else { "OFF" };
-    ctx.say(format!("🔀 シャッフル再生を **{status}** にしました"))
+    let note = if option && was_fair {
+        "（公平モードは OFF にしました）"
+    } else {
+        ""
+    };
+    ctx.say(format!("🔀 シャッフル再生を **{status}** にしました{note}"))
         .await?;
     Ok(())
 }
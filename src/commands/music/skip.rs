@@ -1,4 +1,8 @@
-use crate::util::alias::{Context, Error};
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, check, command_actor},
+    vote::{VoteOutcome, cast_vote},
+};
 
 #[poise::command(slash_command, prefix_command, guild_only)]
 pub async fn skip(
@@ -6,5 +10,29 @@ pub async fn skip(
     #[description = "進む(+) / 戻る(-) の数。省略時は +1"] offset: Option<i32>,
 ) -> Result<(), Error> {
     ctx.defer().await?;
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    // 本人による自分の曲のスキップは 1 曲進める場合のみ
+    let requester = if offset.unwrap_or(1) == 1 {
+        ctx.data()
+            .lavalink_playing
+            .get(&guild_id)
+            .map(|e| e.value().requested_by)
+    } else {
+        None
+    };
+    let actor = command_actor(&ctx).await?;
+    let settings = ctx.data().settings.get(guild_id);
+    if let Err(reason) = check(&settings, &actor, MusicAction::Skip { requester }) {
+        // 投票制なら DJ 以外の 1 曲スキップは投票として扱う
+        if !(settings.vote_skip && offset.unwrap_or(1) == 1) {
+            ctx.say(reason).await?;
+            return Ok(());
+        }
+        let outcome = cast_vote(ctx.cache(), ctx.data(), guild_id, actor.user_id);
+        ctx.say(outcome.message()).await?;
+        if !matches!(outcome, VoteOutcome::Passed) {
+            return Ok(());
+        }
+    }
     crate::commands::music::skip_lavalink::run(&ctx, offset).await
 }
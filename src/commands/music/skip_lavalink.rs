@@ -1,6 +1,6 @@
 use crate::util::{
     alias::{Context, Error},
-    lavalink_player::{play_next_from_queue_lavalink, play_track_req_lavalink},
+    lavalink_player::{play_next_or_autoplay, play_track_req_lavalink},
     music_ui::{control_components, track_embed},
     player::ManualTransitionGuard,
 };
@@ -53,6 +53,7 @@ pub async fn run(ctx: &Context<'_>, offset: Option<i32>) -> Result<(), Error> {
             let target = popped.pop();
             (target, popped)
         };
+        ctx.data().store.mark_dirty(guild_id);
 
         let Some(target) = target else {
             ctx.say("⚠️ 戻れる履歴がありません").await?;
@@ -122,26 +123,26 @@ pub async fn run(ctx: &Context<'_>, offset: Option<i32>) -> Result<(), Error> {
             }
         }
     }
+    if dropped > 0 {
+        ctx.data().store.mark_dirty(guild_id);
+    }
 
-    let res = play_next_from_queue_lavalink(
-        guild_id,
-        lavalink.clone(),
-        queues.clone(),
-        playing.clone(),
-        history.clone(),
-        3,
-    )
-    .await?;
+    // キューが尽きていれば自動再生で関連曲を足して続ける
+    let res = play_next_or_autoplay(guild_id, lavalink.clone(), current_req).await?;
 
     if let Some(started) = res.started {
         if let Some((channel_id, message_id)) =
             ctx.data().now_playing.get(&guild_id).map(|e| *e.value())
         {
             let remaining = queues.get(&guild_id).map(|q| q.len()).unwrap_or(0);
+            let mut info = format!("キュー残り {remaining} 件");
+            if res.autoplayed {
+                info.insert_str(0, "📻 自動再生 / ");
+            }
             let embed = track_embed(
                 "🎵 再生中",
                 Some(&started),
-                Some(format!("キュー残り {remaining} 件")),
+                Some(info),
                 Colour::new(0x5865F2),
             );
             let components = control_components(PlayMode::Play);
@@ -1,6 +1,7 @@
 use crate::util::{
     alias::{Context, Error},
     lavalink_player::stop_and_clear_lavalink,
+    permissions::{MusicAction, require},
     player::ManualTransitionGuard,
 };
 
@@ -9,6 +10,9 @@ pub async fn stop(ctx: Context<'_>) -> Result<(), Error> {
     ctx.defer().await?;
 
     let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Stop).await? {
+        return Ok(());
+    }
     let _guard = ManualTransitionGuard::acquire(&ctx.data().transition_flags, guild_id);
 
     if ctx.data().lavalink.is_none() {
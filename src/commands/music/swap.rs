@@ -0,0 +1,43 @@
+use crate::util::{
+    alias::{Context, Error},
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キューの 2 曲の位置を入れ替えます
+pub async fn swap(
+    ctx: Context<'_>,
+    #[description = "入れ替える曲の位置 (1〜)"] a: usize,
+    #[description = "入れ替える曲の位置 (1〜)"] b: usize,
+) -> Result<(), Error> {
+    let gid = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let result = {
+        let mut q = ctx.data().queues.entry(gid).or_default();
+        if q.config.fair {
+            Err("❌ 公平モード中は並べ替えできません（`/fair false` で解除）".to_string())
+        } else {
+            let len = q.len();
+            let indices = a
+                .checked_sub(1)
+                .and_then(|i| q.index_at(i))
+                .zip(b.checked_sub(1).and_then(|i| q.index_at(i)));
+            match indices {
+                Some((x, y)) if q.swap(x, y) => Ok(()),
+                _ => Err(format!("❌ 有効な範囲は 1〜{len} です")),
+            }
+        }
+    };
+    match result {
+        Ok(()) => {
+            ctx.data().store.mark_dirty(gid);
+            ctx.say(format!("🔁 #{a} と #{b} を入れ替えました")).await?;
+        }
+        Err(msg) => {
+            ctx.say(msg).await?;
+        }
+    }
+    Ok(())
+}
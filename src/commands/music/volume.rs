@@ -0,0 +1,48 @@
+use crate::util::{
+    alias::{Context, Error},
+    filters::MAX_VOLUME,
+    permissions::{MusicAction, require},
+};
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 再生音量を設定します（100 が原音）
+pub async fn volume(
+    ctx: Context<'_>,
+    #[description = "音量 (0-200)"]
+    #[min = 0]
+    #[max = 200]
+    level: u16,
+) -> Result<(), Error> {
+    ctx.defer().await?; // 3秒ルール
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !require(&ctx, MusicAction::Control).await? {
+        return Ok(());
+    }
+    let level = level.min(MAX_VOLUME);
+    let mut q = ctx.data().queues.entry(guild_id).or_default();
+    q.value_mut().set_volume(level);
+    drop(q);
+    ctx.data().store.mark_dirty(guild_id);
+
+    if let Some(player) = ctx
+        .data()
+        .lavalink
+        .as_ref()
+        .and_then(|l| l.get_player_context(guild_id))
+    {
+        player
+            .set_volume(level)
+            .await
+            .map_err(|e| Error::from(format!("failed to set Lavalink volume: {e}")))?;
+    }
+
+    let icon = match level {
+        0 => "🔇",
+        1..=50 => "🔈",
+        51..=120 => "🔉",
+        _ => "🔊",
+    };
+    ctx.say(format!("{icon} 音量を **{level}%** にしました"))
+        .await?;
+    Ok(())
+}
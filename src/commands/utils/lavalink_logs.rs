@@ -0,0 +1,47 @@
+use crate::util::{
+    alias::{Context, Error},
+    lavalink::recent_logs,
+};
+use poise::CreateReply;
+use poise::serenity_prelude::CreateAttachment;
+
+const MAX_INLINE: usize = 1900;
+
+/// 自動起動した Lavalink の直近のログを表示します（オーナー専用）
+#[poise::command(slash_command, prefix_command, owners_only, ephemeral)]
+pub async fn lavalink_logs(
+    ctx: Context<'_>,
+    #[description = "WARN 以上のみ表示 (既定: true)"] errors_only: Option<bool>,
+    #[description = "表示件数 (1-200, 既定: 30)"] count: Option<usize>,
+) -> Result<(), Error> {
+    let min_level = if errors_only.unwrap_or(true) {
+        tracing::Level::WARN
+    } else {
+        tracing::Level::TRACE
+    };
+    let limit = count.unwrap_or(30).clamp(1, 200);
+    let logs = recent_logs(min_level, limit);
+
+    if logs.is_empty() {
+        ctx.say("📭 該当する Lavalink ログはありません").await?;
+        return Ok(());
+    }
+
+    let text = logs
+        .iter()
+        .map(|l| l.line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.len() <= MAX_INLINE {
+        ctx.say(format!("```\n{text}\n```")).await?;
+    } else {
+        let attachment = CreateAttachment::bytes(text.into_bytes(), "lavalink.log");
+        ctx.send(
+            CreateReply::default()
+                .content(format!("📄 Lavalink ログ {} 行", logs.len()))
+                .attachment(attachment),
+        )
+        .await?;
+    }
+    Ok(())
+}
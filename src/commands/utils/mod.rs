@@ -1,2 +1,4 @@
 pub mod capstone;
+pub mod lavalink_logs;
 pub mod nano_chat;
+pub mod settings;
@@ -0,0 +1,400 @@
+use poise::ChoiceParameter;
+use poise::serenity_prelude::{Channel, Mentionable};
+
+use crate::util::{
+    alias::{Context, Error},
+    filters::MAX_VOLUME,
+    music_ui::{format_duration, parse_timestamp},
+    repeat::RepeatMode,
+    search_source::{SearchSource, autocomplete_source},
+    settings::Language,
+};
+
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "show",
+        "prefix",
+        "volume",
+        "queue_limit",
+        "user_limit",
+        "duplicates",
+        "max_duration",
+        "repeat",
+        "announce",
+        "language",
+        "search_source",
+        "playlist_limit",
+        "history_limit",
+        "timeouts"
+    ),
+    subcommand_required,
+    required_permissions = "MANAGE_GUILD"
+)]
+/// サーバーごとの設定を表示・変更します
+pub async fn settings(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 現在の設定を表示します
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let s = ctx.data().settings.get(guild_id);
+    let queue_limit = s
+        .max_queue_length
+        .map(|n| format!("{n} 曲"))
+        .unwrap_or_else(|| "無制限".to_string());
+    let user_limit = s
+        .max_tracks_per_user
+        .map(|n| format!("{n} 曲"))
+        .unwrap_or_else(|| "無制限".to_string());
+    let duplicates = if s.allow_duplicates {
+        "許可"
+    } else {
+        "禁止"
+    };
+    let max_duration = s
+        .max_track_secs
+        .map(|secs| format_duration(Some(std::time::Duration::from_secs(secs))))
+        .unwrap_or_else(|| "無制限".to_string());
+    let announce = s
+        .announce_channel()
+        .map(|c| c.mention().to_string())
+        .unwrap_or_else(|| "未設定（再生パネルのチャンネル）".to_string());
+    ctx.say(format!(
+        "⚙️ **サーバー設定**\n\
+         プレフィックス: `{}`\n\
+         既定の音量: {}%\n\
+         既定のリピート: {:?}\n\
+         キューの上限: {queue_limit}\n\
+         1 人あたりの上限: {user_limit}\n\
+         重複した曲の追加: {duplicates}\n\
+         曲の最大長: {max_duration}\n\
+         プレイリストの追加上限: {} 曲\n\
+         再生履歴の保持数: {} 件\n\
+         パネル操作の受付: {} 秒 / UI 操作の受付: {} 秒\n\
+         通知チャンネル: {announce}\n\
+         言語: {}\n\
+         既定の検索ソース: {}",
+        s.prefix(),
+        s.default_volume,
+        s.default_repeat,
+        s.playlist_limit,
+        s.history_limit,
+        s.panel_timeout().as_secs(),
+        s.ui_timeout().as_secs(),
+        s.language.name(),
+        s.search_source.label(),
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレフィックスコマンドの接頭辞を変更します（省略で既定に戻す）
+pub async fn prefix(
+    ctx: Context<'_>,
+    #[description = "新しい接頭辞 (例: !)"]
+    #[max_length = 8]
+    prefix: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let prefix = prefix
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
+    if prefix
+        .as_ref()
+        .is_some_and(|p| p.chars().any(char::is_whitespace))
+    {
+        ctx.say("❌ 接頭辞に空白は使えません").await?;
+        return Ok(());
+    }
+    let settings = ctx
+        .data()
+        .settings
+        .update(guild_id, |s| s.prefix = prefix)
+        .await?;
+    ctx.say(format!(
+        "⚙️ プレフィックスを `{}` に設定しました",
+        settings.prefix()
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// `/volume` を使っていないときの既定の音量を設定します
+pub async fn volume(
+    ctx: Context<'_>,
+    #[description = "音量 % (0〜200)"]
+    #[min = 0]
+    #[max = 200]
+    level: u16,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let level = level.min(MAX_VOLUME);
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.default_volume = level)
+        .await?;
+    ctx.say(format!("⚙️ 既定の音量を **{level}%** に設定しました"))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キューに入れられる最大曲数を設定します（0 で無制限）
+pub async fn queue_limit(
+    ctx: Context<'_>,
+    #[description = "最大曲数 (0 で無制限)"] limit: usize,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let limit = (limit > 0).then_some(limit);
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.max_queue_length = limit)
+        .await?;
+    match limit {
+        Some(n) => ctx.say(format!("⚙️ キューの上限を **{n} 曲** に設定しました")),
+        None => ctx.say("⚙️ キューの上限を解除しました".to_string()),
+    }
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 1 人がキューに入れられる最大曲数を設定します（0 で無制限）
+pub async fn user_limit(
+    ctx: Context<'_>,
+    #[description = "最大曲数 (0 で無制限)"] limit: usize,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let limit = (limit > 0).then_some(limit);
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.max_tracks_per_user = limit)
+        .await?;
+    match limit {
+        Some(n) => ctx.say(format!(
+            "⚙️ 1 人あたりのキューの上限を **{n} 曲** に設定しました"
+        )),
+        None => ctx.say("⚙️ 1 人あたりのキューの上限を解除しました".to_string()),
+    }
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// キューに入っている曲と同じ URL の追加を許可するかを設定します
+pub async fn duplicates(
+    ctx: Context<'_>,
+    #[description = "許可する場合は true"] allow: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.allow_duplicates = allow)
+        .await?;
+    let status = if allow { "許可" } else { "禁止" };
+    ctx.say(format!("⚙️ 重複した曲の追加を **{status}** しました"))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 追加できる曲の最大長を設定します（0 で無制限）
+pub async fn max_duration(
+    ctx: Context<'_>,
+    #[description = "最大長 (mm:ss / hh:mm:ss / 秒, 0 で無制限)"] duration: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let Some(duration) = parse_timestamp(&duration) else {
+        ctx.say("❌ 時間は `mm:ss` / `hh:mm:ss` / 秒数 で指定してください")
+            .await?;
+        return Ok(());
+    };
+    let secs = (!duration.is_zero()).then(|| duration.as_secs());
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.max_track_secs = secs)
+        .await?;
+    match secs {
+        Some(_) => ctx.say(format!(
+            "⚙️ 曲の最大長を **{}** に設定しました",
+            format_duration(Some(duration))
+        )),
+        None => ctx.say("⚙️ 曲の最大長の制限を解除しました".to_string()),
+    }
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// `/repeat` を使っていないときの既定のリピートモードを設定します
+pub async fn repeat(
+    ctx: Context<'_>,
+    #[description = "Off / Track / Queue"] mode: RepeatMode,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.default_repeat = mode)
+        .await?;
+    ctx.say(format!(
+        "⚙️ 既定のリピートモードを **{mode:?}** に設定しました"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 自動退出や再開の案内を送るチャンネルを設定します（省略で解除）
+pub async fn announce(
+    ctx: Context<'_>,
+    #[description = "通知チャンネル"] channel: Option<Channel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let channel_id = channel.map(|c| c.id());
+    ctx.data()
+        .settings
+        .update(guild_id, |s| {
+            s.announce_channel = channel_id.map(|c| c.get())
+        })
+        .await?;
+    match channel_id {
+        Some(c) => ctx.say(format!(
+            "⚙️ 通知チャンネルを {} に設定しました",
+            c.mention()
+        )),
+        None => ctx
+            .say("⚙️ 通知チャンネルを解除しました（再生パネルのチャンネルへ送ります）".to_string()),
+    }
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// ボットの表示言語を設定します
+pub async fn language(
+    ctx: Context<'_>,
+    #[description = "言語"] language: Language,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.language = language)
+        .await?;
+    ctx.say(format!("⚙️ 言語を **{}** に設定しました", language.name()))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// URL 以外の検索語を検索する既定のサービスを設定します
+pub async fn search_source(
+    ctx: Context<'_>,
+    #[description = "検索ソース (ytsearch / ytmsearch / scsearch など)"]
+    #[autocomplete = "autocomplete_source"]
+    source: SearchSource,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    if !source.is_available() {
+        ctx.say(format!(
+            "❌ {} の検索はこの Lavalink では使えません",
+            source.label()
+        ))
+        .await?;
+        return Ok(());
+    }
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.search_source = source)
+        .await?;
+    ctx.say(format!(
+        "⚙️ 既定の検索ソースを **{}** (`{}`) に設定しました",
+        source.label(),
+        source.prefix()
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// プレイリストから一度に追加する最大曲数を設定します
+pub async fn playlist_limit(
+    ctx: Context<'_>,
+    #[description = "最大曲数 (1〜500)"]
+    #[min = 1]
+    #[max = 500]
+    limit: usize,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let limit = limit.clamp(1, 500);
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.playlist_limit = limit)
+        .await?;
+    ctx.say(format!(
+        "⚙️ プレイリストの追加上限を **{limit} 曲** に設定しました"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 保持する再生履歴の件数を設定します
+pub async fn history_limit(
+    ctx: Context<'_>,
+    #[description = "件数 (1〜500)"]
+    #[min = 1]
+    #[max = 500]
+    limit: usize,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let limit = limit.clamp(1, 500);
+    ctx.data()
+        .settings
+        .update(guild_id, |s| s.history_limit = limit)
+        .await?;
+    ctx.say(format!(
+        "⚙️ 再生履歴の保持数を **{limit} 件** に設定しました"
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, prefix_command, guild_only)]
+/// 再生パネルと `/queue` などの操作を受け付ける時間を設定します
+pub async fn timeouts(
+    ctx: Context<'_>,
+    #[description = "再生パネルの受付時間（秒, 30 以上）"]
+    #[min = 30]
+    panel: Option<u64>,
+    #[description = "/queue などの受付時間（秒, 30 以上）"]
+    #[min = 30]
+    ui: Option<u64>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let settings = ctx
+        .data()
+        .settings
+        .update(guild_id, |s| {
+            if let Some(secs) = panel {
+                s.panel_timeout_secs = secs;
+            }
+            if let Some(secs) = ui {
+                s.ui_timeout_secs = secs;
+            }
+        })
+        .await?;
+    ctx.say(format!(
+        "⚙️ 受付時間: 再生パネル {} 秒 / UI {} 秒",
+        settings.panel_timeout().as_secs(),
+        settings.ui_timeout().as_secs()
+    ))
+    .await?;
+    Ok(())
+}
@@ -45,6 +45,10 @@ pub struct ConfigFile {
     pub yt_dlp: Option<YtDlpSettings>,
     #[serde(default)]
     pub lavalink: Option<LavalinkSettings>,
+    #[serde(default)]
+    pub storage: Option<StorageSettings>,
+    #[serde(default)]
+    pub music: Option<MusicSettings>,
 }
 
 #[derive(Deserialize, Default, Clone)]
@@ -84,7 +88,103 @@ pub struct LavalinkSettings {
     #[serde(default)]
     pub jar_path: Option<String>,
     #[serde(default)]
+    pub startup_timeout_secs: Option<u64>,
+    /// 旧設定（起動後に待つミリ秒）。`startup_timeout_secs` が無いときだけ秒に切り上げて使う
+    #[serde(default)]
     pub startup_wait_ms: Option<u64>,
+    #[serde(default)]
+    pub jvm_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub config_path: Option<String>,
+    #[serde(default)]
+    pub log_buffer_lines: Option<usize>,
+    #[serde(default)]
+    pub nodes: Vec<LavalinkNodeSettings>,
+    /// LavaSrc プラグイン導入済みなら Spotify などのリンクを Lavalink で読み込む。
+    /// 起動時にノードの `/v4/info` が取れた場合はそちらの結果を優先する
+    #[serde(default)]
+    pub lavasrc: bool,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct LavalinkNodeSettings {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub base_url: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub ssl: Option<bool>,
+    #[serde(default)]
+    pub region: Option<String>,
+}
+
+impl LavalinkSettings {
+    /// `/v4/info` が応答するまで待つ上限（秒）。
+    pub fn startup_timeout_secs(&self) -> u64 {
+        let secs = match (self.startup_timeout_secs, self.startup_wait_ms) {
+            (Some(secs), Some(_)) => {
+                tracing::warn!(
+                    "lavalink.startup_wait_ms is deprecated and ignored because startup_timeout_secs is set"
+                );
+                secs
+            }
+            (Some(secs), None) => secs,
+            (None, Some(ms)) => {
+                tracing::warn!(
+                    startup_wait_ms = ms,
+                    "lavalink.startup_wait_ms is deprecated; use startup_timeout_secs instead"
+                );
+                ms.div_ceil(1000)
+            }
+            (None, None) => 60,
+        };
+        secs.clamp(5, 600)
+    }
+
+    /// `[[lavalink.nodes]]` を優先し、未指定なら `base_url` / `password` を単一ノードとして扱う。
+    pub fn node_list(&self) -> Vec<LavalinkNodeSettings> {
+        if !self.nodes.is_empty() {
+            return self.nodes.clone();
+        }
+        self.base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .map(|base_url| LavalinkNodeSettings {
+                name: None,
+                base_url: base_url.to_string(),
+                password: self.password.clone(),
+                ssl: None,
+                region: None,
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl LavalinkNodeSettings {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.base_url.as_str())
+    }
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct StorageSettings {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct MusicSettings {
+    /// 再生パネルを更新する間隔（秒）
+    #[serde(default)]
+    pub panel_update_secs: Option<u64>,
+    /// 無人または再生なしの状態が続いたら退出するまでの秒数（0 で無効）
+    #[serde(default)]
+    pub idle_timeout_secs: Option<u64>,
 }
 
 const fn default_true() -> bool {
@@ -131,6 +231,10 @@ pub static GLOBAL_CONFIG: Lazy<ConfigFile> = Lazy::new(|| {
             yt_dlp: Option<YtDlpSettings>,
             #[serde(default)]
             lavalink: Option<LavalinkSettings>,
+            #[serde(default)]
+            storage: Option<StorageSettings>,
+            #[serde(default)]
+            music: Option<MusicSettings>,
         }
         let optional = toml::from_str::<MaybeYt>(&contents).unwrap_or_default();
         tracing::info!("config parsed (flat keys)");
@@ -141,6 +245,8 @@ pub static GLOBAL_CONFIG: Lazy<ConfigFile> = Lazy::new(|| {
             },
             yt_dlp: optional.yt_dlp,
             lavalink: optional.lavalink,
+            storage: optional.storage,
+            music: optional.music,
         };
     }
 
@@ -159,6 +265,8 @@ pub static GLOBAL_CONFIG: Lazy<ConfigFile> = Lazy::new(|| {
         },
         yt_dlp: None,
         lavalink: None,
+        storage: None,
+        music: None,
     }
 });
 
@@ -229,8 +337,20 @@ fn init_tracing() {
         .ok();
 }
 
+fn dynamic_prefix(
+    ctx: poise::PartialContext<'_, Data, Error>,
+) -> poise::BoxFuture<'_, Result<Option<String>, Error>> {
+    Box::pin(async move {
+        let prefix = match ctx.guild_id {
+            Some(guild_id) => ctx.data.settings.get(guild_id).prefix().to_string(),
+            None => crate::util::settings::DEFAULT_PREFIX.to_string(),
+        };
+        Ok(Some(prefix))
+    })
+}
+
 fn framework_event_handler<'a>(
-    _ctx: &'a poise::serenity_prelude::Context,
+    ctx: &'a poise::serenity_prelude::Context,
     event: &'a FullEvent,
     _framework: poise::FrameworkContext<'a, Data, Error>,
     data: &'a Data,
@@ -252,12 +372,25 @@ fn framework_event_handler<'a>(
             }
             FullEvent::VoiceStateUpdate { new, .. } => {
                 if let Some(guild_id) = new.guild_id {
+                    // 再起動後に再接続できるよう、ボット自身の接続先を記録する。
+                    if new.user_id == ctx.cache.current_user().id {
+                        match new.channel_id {
+                            Some(channel_id) => {
+                                data.voice_channels.insert(guild_id, channel_id);
+                            }
+                            None => {
+                                data.voice_channels.remove(&guild_id);
+                            }
+                        }
+                        data.store.mark_dirty(guild_id);
+                    }
                     lavalink.handle_voice_state_update(
                         guild_id,
                         new.channel_id,
                         new.user_id,
                         new.session_id.clone(),
                     );
+                    crate::util::idle::on_voice_state_update(lavalink.clone(), guild_id).await;
                 }
             }
             _ => {}
@@ -271,17 +404,7 @@ async fn run_bot(shutdown_rx: oneshot::Receiver<()>) {
     init_tracing();
     tracing::info!("bot task started");
     let mut lavalink = crate::util::lavalink::spawn_lavalink(GLOBAL_CONFIG.lavalink.as_ref()).await;
-    if lavalink.is_some() {
-        let wait_ms = GLOBAL_CONFIG
-            .lavalink
-            .as_ref()
-            .and_then(|c| c.startup_wait_ms)
-            .unwrap_or(1500)
-            .clamp(0, 30_000);
-        if wait_ms > 0 {
-            tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
-        }
-    }
+    let lavalink_restarts = lavalink.as_ref().map(|p| p.subscribe_restarts());
     crate::util::lavalink::probe_lavalink(GLOBAL_CONFIG.lavalink.as_ref()).await;
 
     // Windows 環境では yt-dlp の自己更新をバックグラウンドで実行（ブロッキング回避）
@@ -312,7 +435,9 @@ async fn run_bot(shutdown_rx: oneshot::Receiver<()>) {
             commands: create_commands(),
             event_handler: framework_event_handler,
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some("s!".into()),
+                // ギルドごとの設定で変更できるため、固定の prefix は使わない
+                prefix: None,
+                dynamic_prefix: Some(dynamic_prefix),
                 ..Default::default()
             },
             ..Default::default()
@@ -324,15 +449,39 @@ async fn run_bot(shutdown_rx: oneshot::Receiver<()>) {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
                 tracing::info!(user = %ready.user.name, "bot is ready");
                 let mut data = Data::new();
+                let resumable = data.store.restore(&data.queues, &data.history);
+                data.store.spawn_writer(crate::util::storage::SnapshotSources {
+                    queues: data.queues.clone(),
+                    history: data.history.clone(),
+                    playing: data.lavalink_playing.clone(),
+                    now_playing: data.now_playing.clone(),
+                    voice_channels: data.voice_channels.clone(),
+                    positions: data.positions.clone(),
+                });
 
                 if let Some(cfg) = GLOBAL_CONFIG.lavalink.as_ref().filter(|c| c.enabled) {
+                    let songbird = songbird::get(ctx)
+                        .await
+                        .ok_or("Songbird not initialised")?;
                     let runtime = crate::util::lavalink_player::LavalinkRuntimeData {
                         queues: data.queues.clone(),
                         transition_flags: data.transition_flags.clone(),
                         history: data.history.clone(),
                         now_playing: data.now_playing.clone(),
                         lavalink_playing: data.lavalink_playing.clone(),
+                        voice_channels: data.voice_channels.clone(),
+                        positions: data.positions.clone(),
+                        prefetched: data.prefetched.clone(),
+                        fades: data.fades.clone(),
+                        panel_updaters: data.panel_updaters.clone(),
+                        idle_timers: data.idle_timers.clone(),
+                        auto_paused: data.auto_paused.clone(),
+                        votes: data.votes.clone(),
+                        store: data.store.clone(),
+                        settings: data.settings.clone(),
+                        songbird,
                         http: ctx.http.clone(),
+                        cache: ctx.cache.clone(),
                     };
 
                     match crate::util::lavalink_player::build_lavalink_client(
@@ -343,6 +492,25 @@ async fn run_bot(shutdown_rx: oneshot::Receiver<()>) {
                     .await
                     {
                         Ok(client) => {
+                            let labels = cfg
+                                .node_list()
+                                .iter()
+                                .map(|n| n.label().to_string())
+                                .collect::<Vec<_>>();
+                            if labels.len() > 1 {
+                                crate::util::failover::spawn_node_monitor(client.clone(), labels);
+                            }
+                            if let Some(restarts) = lavalink_restarts {
+                                crate::util::failover::spawn_restart_recovery(
+                                    client.clone(),
+                                    restarts,
+                                );
+                            }
+                            crate::util::resume::offer_resume(
+                                ctx.clone(),
+                                client.clone(),
+                                resumable,
+                            );
                             data.lavalink = Some(client);
                             tracing::info!("Lavalink client initialized");
                         }
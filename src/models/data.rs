@@ -1,12 +1,21 @@
 use std::sync::Arc;
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use lavalink_rs::client::LavalinkClient;
 use poise::serenity_prelude::GuildId;
 
-use crate::util::{
-    queue::MusicQueue,
-    types::{HistoryMap, LavalinkPlayingMap, NowPlayingMap, TransitionFlags},
+use crate::{
+    GLOBAL_CONFIG,
+    util::{
+        queue::MusicQueue,
+        saved_playlists::PlaylistStore,
+        settings::SettingsStore,
+        storage::MusicStore,
+        types::{
+            AutoPaused, FadeGenerations, HistoryMap, IdleTimers, LavalinkPlayingMap, NowPlayingMap,
+            PanelUpdaters, PositionMap, PrefetchMap, TransitionFlags, VoiceChannelMap, VoteMap,
+        },
+    },
 };
 
 pub struct Data {
@@ -15,6 +24,17 @@ pub struct Data {
     pub transition_flags: TransitionFlags,
     pub history: HistoryMap,
     pub now_playing: NowPlayingMap,
+    pub voice_channels: VoiceChannelMap,
+    pub positions: PositionMap,
+    pub prefetched: PrefetchMap,
+    pub fades: FadeGenerations,
+    pub panel_updaters: PanelUpdaters,
+    pub idle_timers: IdleTimers,
+    pub auto_paused: AutoPaused,
+    pub votes: VoteMap,
+    pub store: Arc<MusicStore>,
+    pub settings: Arc<SettingsStore>,
+    pub playlists: Arc<PlaylistStore>,
     pub lavalink: Option<Arc<LavalinkClient>>,
 }
 
@@ -26,6 +46,17 @@ impl Data {
             transition_flags: Arc::new(DashMap::new()),
             history: Arc::new(DashMap::new()),
             now_playing: Arc::new(DashMap::new()),
+            voice_channels: Arc::new(DashMap::new()),
+            positions: Arc::new(DashMap::new()),
+            prefetched: Arc::new(DashMap::new()),
+            fades: Arc::new(DashMap::new()),
+            panel_updaters: Arc::new(DashSet::new()),
+            idle_timers: Arc::new(DashMap::new()),
+            auto_paused: Arc::new(DashSet::new()),
+            votes: Arc::new(DashMap::new()),
+            store: Arc::new(MusicStore::from_settings(GLOBAL_CONFIG.storage.as_ref())),
+            settings: Arc::new(SettingsStore::load(GLOBAL_CONFIG.storage.as_ref())),
+            playlists: Arc::new(PlaylistStore::new(GLOBAL_CONFIG.storage.as_ref())),
             lavalink: None,
         }
     }
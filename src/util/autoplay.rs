@@ -0,0 +1,121 @@
+use std::{collections::HashSet, time::Duration};
+
+use lavalink_rs::{
+    client::LavalinkClient,
+    model::{search::SearchEngines, track::TrackLoadData},
+};
+use poise::serenity_prelude::{GuildId, UserId};
+
+use crate::util::{
+    alias::Error,
+    lavalink_player::LavalinkRuntimeData,
+    playlist::expand_youtube_playlist,
+    track::{TrackRequest, youtube_video_id},
+};
+
+/// 自動再生で一度にキューへ足す曲数。
+const AUTOPLAY_BATCH: usize = 5;
+/// 履歴と重なって減る分を見込んで多めに取る。
+const CANDIDATE_LIMIT: usize = 25;
+
+/// キューが空になったとき、最後に再生した曲に関連する曲をキューへ追加する。
+/// 履歴・キューにある曲は除き、追加した件数を返す。
+pub async fn enqueue_related(
+    lavalink: &LavalinkClient,
+    runtime: &LavalinkRuntimeData,
+    guild_id: GuildId,
+    seed: &TrackRequest,
+) -> Result<usize, Error> {
+    let mut candidates = Vec::new();
+    if let Some(id) = youtube_video_id(seed.meta.source_url.as_deref().unwrap_or(&seed.url)) {
+        let mix = format!("https://www.youtube.com/watch?v={id}&list=RD{id}");
+        match expand_youtube_playlist(&mix, CANDIDATE_LIMIT, bot_id(runtime)).await {
+            Ok(reqs) => candidates = reqs,
+            Err(err) => {
+                tracing::debug!(guild = %guild_id, error = %err, "failed to expand youtube mix for autoplay");
+            }
+        }
+    }
+    if candidates.is_empty() {
+        candidates = search_related(lavalink, runtime, guild_id, seed).await?;
+    }
+
+    // 履歴・キュー・直前の曲と同じものは入れない
+    let mut seen: HashSet<String> = runtime
+        .history
+        .get(&guild_id)
+        .map(|h| h.iter().map(TrackRequest::dedupe_key).collect())
+        .unwrap_or_default();
+    seen.insert(seed.dedupe_key());
+    let limits = runtime.settings.get(guild_id).queue_limits();
+    let mut q = runtime.queues.entry(guild_id).or_default();
+    seen.extend(q.iter().map(TrackRequest::dedupe_key));
+
+    let mut added = 0;
+    for req in candidates {
+        if added >= AUTOPLAY_BATCH {
+            break;
+        }
+        if !seen.insert(req.dedupe_key()) {
+            continue;
+        }
+        // 長すぎる曲などキューの制限に掛かるものは飛ばす
+        match q.try_push_back(req, &limits) {
+            Ok(_) => added += 1,
+            Err(err) => {
+                tracing::debug!(guild = %guild_id, reason = %err, "autoplay skipped a related track");
+            }
+        }
+    }
+    drop(q);
+    if added > 0 {
+        runtime.store.mark_dirty(guild_id);
+    }
+    tracing::info!(guild = %guild_id, seed = %seed.url, added, "autoplay enqueued related tracks");
+    Ok(added)
+}
+
+/// アーティスト名と曲名で YouTube を検索する（ミックスを取得できない曲向け）。
+async fn search_related(
+    lavalink: &LavalinkClient,
+    runtime: &LavalinkRuntimeData,
+    guild_id: GuildId,
+    seed: &TrackRequest,
+) -> Result<Vec<TrackRequest>, Error> {
+    let title = seed.meta.title.as_deref().unwrap_or_default();
+    let artist = seed.meta.artist.as_deref().unwrap_or_default();
+    let terms = format!("{artist} {title}");
+    if terms.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let query = SearchEngines::YouTube
+        .to_query(terms.trim())
+        .map_err(|e| Error::from(format!("Lavalink search query error: {e}")))?;
+    let load = lavalink
+        .load_tracks(guild_id, &query)
+        .await
+        .map_err(|e| Error::from(format!("Lavalink search load_tracks error: {e}")))?;
+    let Some(TrackLoadData::Search(tracks)) = load.data else {
+        return Ok(Vec::new());
+    };
+    Ok(tracks
+        .into_iter()
+        .filter(|t| !t.info.is_stream)
+        .filter_map(|t| {
+            let uri = t.info.uri.clone()?;
+            let mut req = TrackRequest::new(uri.clone(), bot_id(runtime));
+            req.meta.title = Some(t.info.title.clone());
+            req.meta.artist = Some(t.info.author.clone()).filter(|a| !a.trim().is_empty());
+            req.meta.duration = Some(Duration::from_millis(t.info.length));
+            req.meta.source_url = Some(uri);
+            req.meta.thumbnail = t.info.artwork_url.clone();
+            Some(req)
+        })
+        .take(CANDIDATE_LIMIT)
+        .collect())
+}
+
+/// 自動再生で追加した曲のリクエスト者（ボット自身）。
+fn bot_id(runtime: &LavalinkRuntimeData) -> UserId {
+    runtime.cache.current_user().id
+}
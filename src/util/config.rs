@@ -1,16 +1,39 @@
-use crate::util::repeat::RepeatMode;
+use crate::util::{filters::FilterPreset, repeat::RepeatMode};
 
 #[derive(Debug)]
 pub struct MusicConfig {
-    pub repeat_mode: RepeatMode,
+    /// 未設定ならギルド設定の既定リピートモード
+    pub repeat_mode: Option<RepeatMode>,
     pub shuffle: bool,
+    /// リクエスト者ごとに順番に再生する（シャッフルとは排他）
+    pub fair: bool,
+    /// キューが空になったら関連する曲を自動で追加する
+    pub autoplay: bool,
+    /// 曲間のクロスフェード秒数（0 で無効）
+    pub crossfade_secs: u64,
+    /// プレイヤー音量（0〜200、100 が原音）。未設定ならギルド設定の既定音量
+    pub volume: Option<u16>,
+    pub filter: FilterPreset,
+    /// `FilterPreset::Custom` 時のイコライザーゲイン（バンド 0 から順）
+    pub custom_eq: Vec<f64>,
 }
 
 impl MusicConfig {
     pub fn new() -> Self {
         Self {
-            repeat_mode: RepeatMode::Off,
+            repeat_mode: None,
             shuffle: false,
+            fair: false,
+            autoplay: false,
+            crossfade_secs: 0,
+            volume: None,
+            filter: FilterPreset::Off,
+            custom_eq: Vec::new(),
         }
     }
+
+    /// 実際に適用するリピートモード。
+    pub fn repeat_mode_or(&self, default: RepeatMode) -> RepeatMode {
+        self.repeat_mode.unwrap_or(default)
+    }
 }
@@ -0,0 +1,182 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use lavalink_rs::{client::LavalinkClient, model::track::TrackData, player_context::PlayerContext};
+use poise::serenity_prelude::GuildId;
+
+use crate::util::{
+    alias::Error, filters::guild_filters, lavalink_player::LavalinkRuntimeData, queue::MusicQueue,
+};
+
+/// 音量を変更する間隔。細かすぎると Lavalink への PATCH が増える。
+const FADE_STEP: Duration = Duration::from_millis(250);
+/// 終盤のフェードアウト開始を待つ間、実位置を確認し直す間隔（一時停止・シーク対策）。
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+pub const MAX_CROSSFADE_SECS: u64 = 12;
+
+/// 曲の切り替え前に呼ぶ。進行中のフェードを打ち切り、クロスフェード有効時はフェード秒数と世代番号を返す。
+/// 有効時は呼び出し側で音量倍率 0 のフィルターを付けて再生を始めること。
+pub fn begin_transition(lavalink: &LavalinkClient, guild_id: GuildId) -> Option<(u64, u64)> {
+    let runtime = lavalink.data::<LavalinkRuntimeData>().ok()?;
+    let secs = runtime
+        .queues
+        .get(&guild_id)
+        .map(|q| q.config.crossfade_secs)
+        .unwrap_or(0);
+
+    if secs == 0 {
+        if let Some((_, generation)) = runtime.fades.remove(&guild_id) {
+            generation.fetch_add(1, Ordering::AcqRel);
+        }
+        return None;
+    }
+
+    let generation = runtime
+        .fades
+        .entry(guild_id)
+        .or_insert_with(|| Arc::new(AtomicU64::new(0)))
+        .clone();
+    let token = generation.fetch_add(1, Ordering::AcqRel) + 1;
+    Some((secs, token))
+}
+
+/// シーク後に呼ぶ。進行中のフェードを打ち切って音量を戻し、終盤のフェードアウトを張り直す。
+pub fn restart_after_seek(lavalink: Arc<LavalinkClient>, guild_id: GuildId, track: &TrackData) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let secs = runtime
+        .queues
+        .get(&guild_id)
+        .map(|q| q.config.crossfade_secs)
+        .unwrap_or(0);
+    let Some(generation) = runtime.fades.get(&guild_id).map(|e| e.value().clone()) else {
+        return;
+    };
+    let token = generation.fetch_add(1, Ordering::AcqRel) + 1;
+    let Some(player) = lavalink.get_player_context(guild_id) else {
+        return;
+    };
+    let track = track.clone();
+    tokio::spawn(async move {
+        let _ = set_volume(&player, &runtime.queues, guild_id, 1.0).await;
+        if secs > 0 {
+            spawn_fades(lavalink, guild_id, (secs, token), &track, false);
+        }
+    });
+}
+
+/// 再生開始後のフェードイン（`fade_in` 時）、および終盤のフェードアウトを行うタスクを起動する。
+pub fn spawn_fades(
+    lavalink: Arc<LavalinkClient>,
+    guild_id: GuildId,
+    (secs, token): (u64, u64),
+    track: &TrackData,
+    fade_in: bool,
+) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let Some(generation) = runtime.fades.get(&guild_id).map(|e| e.value().clone()) else {
+        return;
+    };
+    let Some(player) = lavalink.get_player_context(guild_id) else {
+        return;
+    };
+    let fade = Duration::from_secs(secs);
+    let length = (!track.info.is_stream).then(|| Duration::from_millis(track.info.length));
+
+    tokio::spawn(async move {
+        let fader = Fader {
+            player: &player,
+            queues: &runtime.queues,
+            guild_id,
+            generation: &generation,
+            token,
+        };
+        if fade_in && !fader.ramp(0.0, 1.0, fade).await {
+            return;
+        }
+        // 短い曲やライブ配信ではフェードアウトしない
+        let Some(length) = length.filter(|l| *l > fade * 2) else {
+            return;
+        };
+
+        let remaining = loop {
+            if !fader.is_current() {
+                return;
+            }
+            let Ok(state) = player.get_player().await else {
+                return;
+            };
+            if state.track.is_none() {
+                return;
+            }
+            let remaining = length.saturating_sub(Duration::from_millis(state.state.position));
+            if remaining <= fade && !state.paused {
+                break remaining;
+            }
+            let wait = if state.paused {
+                RECHECK_INTERVAL
+            } else {
+                (remaining - fade).min(RECHECK_INTERVAL)
+            };
+            tokio::time::sleep(wait).await;
+        };
+        fader.ramp(1.0, 0.0, remaining).await;
+    });
+}
+
+struct Fader<'a> {
+    player: &'a PlayerContext,
+    queues: &'a DashMap<GuildId, MusicQueue>,
+    guild_id: GuildId,
+    generation: &'a AtomicU64,
+    token: u64,
+}
+
+impl Fader<'_> {
+    fn is_current(&self) -> bool {
+        self.generation.load(Ordering::Acquire) == self.token
+    }
+
+    /// `from` から `to` へ音量を段階的に変える。途中で世代が変わったら false を返して中断する。
+    async fn ramp(&self, from: f64, to: f64, duration: Duration) -> bool {
+        let steps = (duration.as_millis() / FADE_STEP.as_millis()).max(1) as u32;
+        let interval = duration / steps;
+        for step in 1..=steps {
+            tokio::time::sleep(interval).await;
+            if !self.is_current() {
+                return false;
+            }
+            let volume = from + (to - from) * f64::from(step) / f64::from(steps);
+            if set_volume(self.player, self.queues, self.guild_id, volume)
+                .await
+                .is_err()
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// 音量倍率をギルドのフィルター設定に重ねて適用する。
+async fn set_volume(
+    player: &PlayerContext,
+    queues: &DashMap<GuildId, MusicQueue>,
+    guild_id: GuildId,
+    volume: f64,
+) -> Result<(), Error> {
+    player
+        .set_filters(guild_filters(queues, guild_id, Some(volume)))
+        .await
+        .map_err(|e| Error::from(format!("failed to set Lavalink volume filter: {e}")))?;
+    Ok(())
+}
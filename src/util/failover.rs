@@ -0,0 +1,122 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use lavalink_rs::client::LavalinkClient;
+use tokio::{sync::watch, time::MissedTickBehavior};
+
+use crate::util::{lavalink_player::LavalinkRuntimeData, resume::recreate_player};
+
+const NODE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Lavalink 再起動後、WebSocket の再接続を待つ上限。
+const RECONNECT_WAIT: Duration = Duration::from_secs(30);
+
+/// ノードの死活を定期的に確認し、停止したノード上のプレイヤーを稼働中のノードへ移す。
+/// `labels` は `LavalinkSettings::node_list()` と同じ順序のノード名。
+pub fn spawn_node_monitor(lavalink: Arc<LavalinkClient>, labels: Vec<String>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(NODE_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut down = HashSet::<usize>::new();
+
+        loop {
+            interval.tick().await;
+
+            let mut any_running = false;
+            for (idx, node) in lavalink.nodes.iter().enumerate() {
+                let label = labels.get(idx).map(String::as_str).unwrap_or("?");
+                if node.is_running.load(Ordering::SeqCst) {
+                    any_running = true;
+                    if down.remove(&idx) {
+                        tracing::info!(node = %label, "Lavalink node recovered");
+                    }
+                } else if down.insert(idx) {
+                    tracing::warn!(node = %label, "Lavalink node is down");
+                }
+            }
+            if down.is_empty() {
+                continue;
+            }
+            if !any_running {
+                tracing::warn!("no Lavalink node is running; player migration postponed");
+                continue;
+            }
+
+            let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+                continue;
+            };
+            let guilds = runtime
+                .voice_channels
+                .iter()
+                .map(|e| *e.key())
+                .collect::<Vec<_>>();
+            for guild_id in guilds {
+                if lavalink.get_player_context(guild_id).is_none() {
+                    continue;
+                }
+                let node = lavalink.get_node_for_guild(guild_id).await;
+                if node.is_running.load(Ordering::SeqCst) {
+                    continue;
+                }
+                match recreate_player(lavalink.clone(), guild_id).await {
+                    Ok(Some(track)) => tracing::info!(
+                        guild = %guild_id,
+                        title = track.meta.title.as_deref().unwrap_or(&track.url),
+                        "migrated Lavalink player to a healthy node"
+                    ),
+                    Ok(None) => tracing::info!(
+                        guild = %guild_id,
+                        "recreated idle Lavalink player on a healthy node"
+                    ),
+                    Err(err) => tracing::warn!(
+                        guild = %guild_id,
+                        error = %err,
+                        "failed to migrate Lavalink player"
+                    ),
+                }
+            }
+        }
+    });
+}
+
+/// 自動起動した Lavalink が再起動したら、接続中だったギルドのプレイヤーを作り直す。
+pub fn spawn_restart_recovery(lavalink: Arc<LavalinkClient>, mut restarts: watch::Receiver<u64>) {
+    tokio::spawn(async move {
+        while restarts.changed().await.is_ok() {
+            let generation = *restarts.borrow_and_update();
+            tracing::info!(generation, "Lavalink restarted; recreating players");
+
+            let deadline = tokio::time::Instant::now() + RECONNECT_WAIT;
+            while !lavalink
+                .nodes
+                .iter()
+                .any(|n| n.is_running.load(Ordering::SeqCst))
+            {
+                if tokio::time::Instant::now() >= deadline {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+
+            let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+                continue;
+            };
+            let guilds = runtime
+                .voice_channels
+                .iter()
+                .map(|e| *e.key())
+                .collect::<Vec<_>>();
+            for guild_id in guilds {
+                if let Err(err) = recreate_player(lavalink.clone(), guild_id).await {
+                    tracing::warn!(
+                        guild = %guild_id,
+                        error = %err,
+                        "failed to recreate Lavalink player after restart"
+                    );
+                }
+            }
+        }
+    });
+}
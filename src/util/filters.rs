@@ -0,0 +1,132 @@
+use dashmap::DashMap;
+use lavalink_rs::model::player::{Equalizer, Filters, Karaoke, LowPass, Rotation, Timescale};
+use poise::ChoiceParameter;
+use poise::serenity_prelude::GuildId;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{config::MusicConfig, queue::MusicQueue, settings::SettingsStore};
+
+/// Lavalink のイコライザーのバンド数（25Hz〜16kHz）。
+pub const EQ_BANDS: usize = 15;
+pub const MAX_VOLUME: u16 = 200;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ChoiceParameter, Serialize, Deserialize)]
+pub enum FilterPreset {
+    #[default]
+    #[name = "Off"]
+    Off,
+    #[name = "Bass Boost"]
+    BassBoost,
+    #[name = "Nightcore"]
+    Nightcore,
+    #[name = "Vaporwave"]
+    Vaporwave,
+    #[name = "8D"]
+    EightD,
+    #[name = "Karaoke"]
+    Karaoke,
+    #[name = "Low Pass"]
+    LowPass,
+    #[name = "Custom EQ"]
+    Custom,
+}
+
+/// `0.25,0.2,0,-0.1` のようなカンマ区切りのゲイン列をイコライザー設定に変換する。
+/// 各値は Lavalink の範囲 (-0.25〜1.0) に丸め、足りないバンドは 0 とする。
+pub fn parse_equalizer(s: &str) -> Option<Vec<f64>> {
+    let gains = s
+        .split([',', ' '])
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| p.trim().parse::<f64>().ok().map(|g| g.clamp(-0.25, 1.0)))
+        .collect::<Option<Vec<_>>>()?;
+    if gains.is_empty() || gains.len() > EQ_BANDS {
+        return None;
+    }
+    Some(gains)
+}
+
+fn equalizer(gains: &[f64]) -> Vec<Equalizer> {
+    gains
+        .iter()
+        .enumerate()
+        .map(|(band, &gain)| Equalizer {
+            band: band as u8,
+            gain,
+        })
+        .collect()
+}
+
+/// ギルドの設定からフィルターを組み立てる。`fade` はクロスフェード中の音量倍率。
+pub fn build_filters(config: &MusicConfig, fade: Option<f64>) -> Filters {
+    let mut filters = Filters {
+        volume: fade,
+        ..Default::default()
+    };
+    match config.filter {
+        FilterPreset::Off => {}
+        FilterPreset::BassBoost => {
+            filters.equalizer = Some(equalizer(&[0.25, 0.2, 0.15, 0.1, 0.05]));
+        }
+        FilterPreset::Nightcore => {
+            filters.timescale = Some(Timescale {
+                speed: Some(1.2),
+                pitch: Some(1.2),
+                rate: Some(1.0),
+            });
+        }
+        FilterPreset::Vaporwave => {
+            filters.timescale = Some(Timescale {
+                speed: Some(0.85),
+                pitch: Some(0.8),
+                rate: Some(1.0),
+            });
+        }
+        FilterPreset::EightD => {
+            filters.rotation = Some(Rotation {
+                rotation_hz: Some(0.2),
+            });
+        }
+        FilterPreset::Karaoke => {
+            filters.karaoke = Some(Karaoke {
+                level: Some(1.0),
+                mono_level: Some(1.0),
+                filter_band: Some(220.0),
+                filter_width: Some(100.0),
+            });
+        }
+        FilterPreset::LowPass => {
+            filters.low_pass = Some(LowPass {
+                smoothing: Some(20.0),
+            });
+        }
+        FilterPreset::Custom => {
+            filters.equalizer = Some(equalizer(&config.custom_eq));
+        }
+    }
+    filters
+}
+
+/// キュー設定を参照してフィルターを組み立てる（キュー未作成時は既定値）。
+pub fn guild_filters(
+    queues: &DashMap<GuildId, MusicQueue>,
+    guild_id: GuildId,
+    fade: Option<f64>,
+) -> Filters {
+    match queues.get(&guild_id) {
+        Some(q) => build_filters(&q.config, fade),
+        None => build_filters(&MusicConfig::new(), fade),
+    }
+}
+
+/// 設定中の音量（0〜200, Lavalink のプレイヤー音量）。未設定ならギルドの既定音量。
+pub fn guild_volume(
+    queues: &DashMap<GuildId, MusicQueue>,
+    settings: &SettingsStore,
+    guild_id: GuildId,
+) -> u16 {
+    queues
+        .get(&guild_id)
+        .and_then(|q| q.config.volume)
+        .unwrap_or_else(|| settings.get(guild_id).default_volume)
+        .min(MAX_VOLUME)
+}
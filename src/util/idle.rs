@@ -0,0 +1,198 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use lavalink_rs::client::LavalinkClient;
+use poise::serenity_prelude::{
+    Cache, ChannelId, Colour, CreateMessage, EditMessage, GuildId, UserId,
+};
+
+use crate::{
+    GLOBAL_CONFIG,
+    util::{
+        lavalink_player::{
+            LavalinkRuntimeData, delete_player, pause_current_lavalink, resume_current_lavalink,
+        },
+        music_ui::track_embed,
+        player::PlaybackControlResult,
+    },
+};
+
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+
+/// タイマーごとに一意な番号（古いタイマーの発火を無視するため）。
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(1);
+
+/// 無人・無再生のまま待つ秒数（0 なら自動退出しない）。
+fn idle_timeout() -> Option<Duration> {
+    let secs = GLOBAL_CONFIG
+        .music
+        .as_ref()
+        .and_then(|m| m.idle_timeout_secs)
+        .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// ボイスチャンネルにいるボット以外のメンバー。キャッシュに無ければ None。
+pub(crate) fn listeners_in_channel(
+    cache: &Cache,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Option<Vec<UserId>> {
+    let guild = cache.guild(guild_id)?;
+    let listeners = guild
+        .voice_states
+        .values()
+        .filter(|vs| vs.channel_id == Some(channel_id))
+        .filter(|vs| {
+            let is_bot = vs
+                .member
+                .as_ref()
+                .map(|m| m.user.bot)
+                .or_else(|| guild.members.get(&vs.user_id).map(|m| m.user.bot))
+                .unwrap_or(false);
+            !is_bot
+        })
+        .map(|vs| vs.user_id)
+        .collect();
+    Some(listeners)
+}
+
+fn humans_in_channel(cache: &Cache, guild_id: GuildId, channel_id: ChannelId) -> Option<usize> {
+    listeners_in_channel(cache, guild_id, channel_id).map(|l| l.len())
+}
+
+/// ボイスの状態変化を受けて、無人なら一時停止して退出タイマーを開始し、人が戻れば再開する。
+pub async fn on_voice_state_update(lavalink: Arc<LavalinkClient>, guild_id: GuildId) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let Some(channel_id) = runtime.voice_channels.get(&guild_id).map(|e| *e.value()) else {
+        // ボット自身が退出済み
+        runtime.idle_timers.remove(&guild_id);
+        runtime.auto_paused.remove(&guild_id);
+        return;
+    };
+    let Some(humans) = humans_in_channel(&runtime.cache, guild_id, channel_id) else {
+        return;
+    };
+
+    if humans == 0 {
+        if let Ok(PlaybackControlResult::Changed(_)) =
+            pause_current_lavalink(&lavalink, guild_id, &runtime.lavalink_playing).await
+        {
+            tracing::info!(guild = %guild_id, "voice channel is empty; pausing playback");
+            runtime.auto_paused.insert(guild_id);
+        }
+        if !runtime.idle_timers.contains_key(&guild_id) {
+            start_timer(lavalink.clone(), guild_id);
+        }
+        return;
+    }
+
+    if runtime.auto_paused.remove(&guild_id).is_some() {
+        tracing::info!(guild = %guild_id, "listener returned; resuming playback");
+        let _ = resume_current_lavalink(&lavalink, guild_id, &runtime.lavalink_playing).await;
+    }
+    if runtime.lavalink_playing.contains_key(&guild_id) {
+        runtime.idle_timers.remove(&guild_id);
+    }
+}
+
+/// 退出タイマーを（既存のものを置き換えて）開始する。キューを再生し終えたときにも呼ぶ。
+pub fn start_timer(lavalink: Arc<LavalinkClient>, guild_id: GuildId) {
+    let Some(timeout) = idle_timeout() else {
+        return;
+    };
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let token = NEXT_TIMER.fetch_add(1, Ordering::Relaxed);
+    runtime.idle_timers.insert(guild_id, token);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(timeout).await;
+        if runtime.idle_timers.get(&guild_id).map(|e| *e.value()) != Some(token) {
+            return;
+        }
+        runtime.idle_timers.remove(&guild_id);
+
+        let Some(channel_id) = runtime.voice_channels.get(&guild_id).map(|e| *e.value()) else {
+            return;
+        };
+        let empty = humans_in_channel(&runtime.cache, guild_id, channel_id) == Some(0);
+        let idle = !runtime.lavalink_playing.contains_key(&guild_id);
+        if !empty && !idle {
+            return;
+        }
+
+        let reason = if empty {
+            "ボイスチャンネルに誰もいなくなったため退出しました。"
+        } else {
+            "しばらく再生がなかったため退出しました。"
+        };
+        if let Err(err) = leave_idle(&lavalink, &runtime, guild_id, reason).await {
+            tracing::warn!(guild = %guild_id, error = %err, "failed to auto-leave voice channel");
+        }
+    });
+}
+
+/// プレイヤーを破棄してボイスチャンネルから退出し、通知チャンネル（未設定なら再生パネルのチャンネル）へ知らせる。
+/// 再生中だった曲はキューの先頭へ戻す。
+async fn leave_idle(
+    lavalink: &LavalinkClient,
+    runtime: &LavalinkRuntimeData,
+    guild_id: GuildId,
+    reason: &str,
+) -> Result<(), crate::Error> {
+    tracing::info!(guild = %guild_id, reason, "auto-leaving voice channel");
+
+    delete_player(lavalink, guild_id).await?;
+    if let Some((_, current)) = runtime.lavalink_playing.remove(&guild_id) {
+        runtime
+            .queues
+            .entry(guild_id)
+            .or_default()
+            .push_front(current);
+    }
+    runtime.prefetched.remove(&guild_id);
+    runtime.fades.remove(&guild_id);
+    runtime.auto_paused.remove(&guild_id);
+    runtime.songbird.remove(guild_id).await?;
+    runtime.store.mark_dirty(guild_id);
+
+    let panel = runtime.now_playing.remove(&guild_id).map(|(_, v)| v);
+    if let Some((channel_id, message_id)) = panel {
+        let _ = channel_id
+            .edit_message(
+                &runtime.http,
+                message_id,
+                EditMessage::new().components(Vec::new()),
+            )
+            .await;
+    }
+    // 通知チャンネルが設定されていればそちらへ、無ければ再生パネルのチャンネルへ送る
+    let target = runtime
+        .settings
+        .get(guild_id)
+        .announce_channel()
+        .or(panel.map(|(channel_id, _)| channel_id));
+    if let Some(channel_id) = target {
+        let embed = track_embed(
+            "👋 ボイスチャンネルから退出しました",
+            None,
+            Some(format!(
+                "{reason}\n`/play` でキューの続きから再生できます。"
+            )),
+            Colour::new(0x5865F2),
+        );
+        channel_id
+            .send_message(&runtime.http, CreateMessage::new().embed(embed))
+            .await?;
+    }
+    Ok(())
+}
@@ -1,22 +1,76 @@
 use std::{
+    collections::VecDeque,
     path::{Path, PathBuf},
     process::Stdio,
+    sync::Mutex,
+    time::Instant,
 };
 
+use once_cell::sync::Lazy;
 use serde::Deserialize;
 use tokio::{
-    io::{AsyncBufReadExt, BufReader},
+    io::{AsyncBufRead, AsyncBufReadExt, BufReader},
     process::{Child, Command},
+    sync::watch,
     task::JoinHandle,
     time::{Duration, timeout},
 };
 
-use crate::{LavalinkSettings, get_http_client};
+use crate::{
+    GLOBAL_CONFIG, LavalinkNodeSettings, LavalinkSettings, get_http_client, util::search_source,
+};
+
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(2);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// これ以上動作していたプロセスが落ちた場合はバックオフを初期値に戻す。
+const STABLE_RUN: Duration = Duration::from_secs(120);
+const DEFAULT_LOG_BUFFER_LINES: usize = 200;
+
+/// Lavalink プロセスの直近のログ（管理コマンドから参照する）。
+static RECENT_LOGS: Lazy<Mutex<VecDeque<LavalinkLogLine>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Clone, Debug)]
+pub struct LavalinkLogLine {
+    pub level: tracing::Level,
+    pub line: String,
+}
 
+/// 自動起動した Lavalink を監視し、終了時に再起動するスーパーバイザーへのハンドル。
 pub struct LavalinkProcess {
+    stop_tx: watch::Sender<bool>,
+    restarts: watch::Receiver<u64>,
+    task: JoinHandle<()>,
+}
+
+impl LavalinkProcess {
+    /// 再起動が完了するたびに増える世代番号を購読する。
+    pub fn subscribe_restarts(&self) -> watch::Receiver<u64> {
+        self.restarts.clone()
+    }
+}
+
+struct RunningChild {
     child: Child,
     stdout_task: JoinHandle<()>,
     stderr_task: JoinHandle<()>,
+    started_at: Instant,
+}
+
+impl RunningChild {
+    fn abort_readers(&self) {
+        self.stdout_task.abort();
+        self.stderr_task.abort();
+    }
+}
+
+struct LaunchSpec {
+    program: PathBuf,
+    working_dir: PathBuf,
+    jar_path: PathBuf,
+    jvm_args: Vec<String>,
+    config_path: Option<PathBuf>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -25,6 +79,8 @@ struct LavalinkInfo {
     version: Option<LavalinkVersion>,
     #[serde(default)]
     plugins: Vec<LavalinkPlugin>,
+    #[serde(default, rename = "sourceManagers")]
+    source_managers: Vec<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -56,18 +112,7 @@ fn resolve_file(base: &Path, raw: &str) -> PathBuf {
     base.join(p)
 }
 
-pub async fn spawn_lavalink(cfg: Option<&LavalinkSettings>) -> Option<LavalinkProcess> {
-    let Some(cfg) = cfg else {
-        return None;
-    };
-    if !cfg.enabled {
-        return None;
-    }
-    if !cfg.auto_start {
-        tracing::info!("lavalink auto-start disabled by config");
-        return None;
-    }
-
+fn launch_spec(cfg: &LavalinkSettings) -> Option<LaunchSpec> {
     let working_dir = resolve_dir(cfg.working_dir.as_deref().unwrap_or("src/lavalink"));
     let java_raw = cfg.java_path.as_deref().unwrap_or(if cfg!(windows) {
         "jdk-17/bin/java.exe"
@@ -87,6 +132,18 @@ pub async fn spawn_lavalink(cfg: Option<&LavalinkSettings>) -> Option<LavalinkPr
         return None;
     }
 
+    let config_path = match cfg.config_path.as_deref().map(str::trim) {
+        Some(raw) if !raw.is_empty() => {
+            let path = resolve_file(&working_dir, raw);
+            if !path.is_file() {
+                tracing::error!(path = %path.display(), "Lavalink config file not found");
+                return None;
+            }
+            Some(path)
+        }
+        _ => None,
+    };
+
     // If bundled java is missing, fall back to PATH java.
     let program = if java_path.is_file() {
         java_path.clone()
@@ -98,17 +155,39 @@ pub async fn spawn_lavalink(cfg: Option<&LavalinkSettings>) -> Option<LavalinkPr
         PathBuf::from("java")
     };
 
-    let mut cmd = Command::new(&program);
-    cmd.current_dir(&working_dir)
-        .arg("-jar")
-        .arg(&jar_path)
+    Some(LaunchSpec {
+        program,
+        working_dir,
+        jar_path,
+        jvm_args: cfg
+            .jvm_args
+            .iter()
+            .flatten()
+            .filter(|a| !a.trim().is_empty())
+            .cloned()
+            .collect(),
+        config_path,
+    })
+}
+
+fn spawn_child(spec: &LaunchSpec) -> Option<RunningChild> {
+    let mut cmd = Command::new(&spec.program);
+    cmd.current_dir(&spec.working_dir).args(&spec.jvm_args);
+    if let Some(path) = spec.config_path.as_ref() {
+        cmd.arg(format!("-Dspring.config.location=file:{}", path.display()));
+    }
+    cmd.arg("-jar")
+        .arg(&spec.jar_path)
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
 
     tracing::info!(
-        working_dir = %working_dir.display(),
-        java = %program.display(),
-        jar = %jar_path.display(),
+        working_dir = %spec.working_dir.display(),
+        java = %spec.program.display(),
+        jar = %spec.jar_path.display(),
+        jvm_args = ?spec.jvm_args,
+        config = ?spec.config_path,
         "starting Lavalink process"
     );
 
@@ -124,41 +203,150 @@ pub async fn spawn_lavalink(cfg: Option<&LavalinkSettings>) -> Option<LavalinkPr
     let stderr = child.stderr.take();
     let stdout_task = tokio::spawn(async move {
         if let Some(out) = stdout {
-            let mut lines = BufReader::new(out).lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                println!("[lavalink] {line}");
-            }
+            forward_logs(BufReader::new(out), tracing::Level::INFO).await;
         }
     });
     let stderr_task = tokio::spawn(async move {
         if let Some(err) = stderr {
-            let mut lines = BufReader::new(err).lines();
-            while let Ok(Some(line)) = lines.next_line().await {
-                eprintln!("[lavalink] {line}");
-            }
+            forward_logs(BufReader::new(err), tracing::Level::WARN).await;
         }
     });
 
-    Some(LavalinkProcess {
+    Some(RunningChild {
         child,
         stdout_task,
         stderr_task,
+        started_at: Instant::now(),
     })
 }
 
-pub async fn shutdown_lavalink(mut proc: Option<LavalinkProcess>) {
-    let Some(mut proc) = proc.take() else {
+/// Logback (Spring Boot) 形式の行からレベル・ロガー・本文を取り出す。
+/// 例: `2024-01-01T00:00:00.000Z  INFO 1234 --- [main] lavalink.server.Launcher : Started`
+fn parse_log_line(line: &str) -> Option<(tracing::Level, &str, &str)> {
+    let level = line
+        .split_whitespace()
+        .take(4)
+        .find_map(|token| match token {
+            "TRACE" => Some(tracing::Level::TRACE),
+            "DEBUG" => Some(tracing::Level::DEBUG),
+            "INFO" => Some(tracing::Level::INFO),
+            "WARN" => Some(tracing::Level::WARN),
+            "ERROR" => Some(tracing::Level::ERROR),
+            _ => None,
+        })?;
+    let (head, message) = line.split_once(" : ")?;
+    let logger = head.rsplit_once(']').map(|(_, l)| l).unwrap_or(head).trim();
+    Some((level, logger, message.trim_end()))
+}
+
+fn emit_log(level: tracing::Level, logger: &str, message: &str) {
+    match level {
+        tracing::Level::ERROR => tracing::error!(target: "lavalink", logger, "{message}"),
+        tracing::Level::WARN => tracing::warn!(target: "lavalink", logger, "{message}"),
+        tracing::Level::INFO => tracing::info!(target: "lavalink", logger, "{message}"),
+        tracing::Level::DEBUG => tracing::debug!(target: "lavalink", logger, "{message}"),
+        tracing::Level::TRACE => tracing::trace!(target: "lavalink", logger, "{message}"),
+    }
+}
+
+fn remember_log(level: tracing::Level, line: String) {
+    let capacity = GLOBAL_CONFIG
+        .lavalink
+        .as_ref()
+        .and_then(|c| c.log_buffer_lines)
+        .unwrap_or(DEFAULT_LOG_BUFFER_LINES);
+    if capacity == 0 {
+        return;
+    }
+    let Ok(mut logs) = RECENT_LOGS.lock() else {
         return;
     };
+    logs.push_back(LavalinkLogLine { level, line });
+    while logs.len() > capacity {
+        logs.pop_front();
+    }
+}
+
+/// Lavalink の出力を tracing イベントとして再発行する。
+/// 形式に合わない行（スタックトレース等）は直前の行のレベル・ロガーを引き継ぐ。
+async fn forward_logs<R: AsyncBufRead + Unpin>(reader: R, fallback: tracing::Level) {
+    let mut lines = reader.lines();
+    let mut last_level = fallback;
+    let mut last_logger = String::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_log_line(&line) {
+            Some((level, logger, message)) => {
+                emit_log(level, logger, message);
+                last_level = level;
+                last_logger.clear();
+                last_logger.push_str(logger);
+            }
+            None => emit_log(last_level, &last_logger, &line),
+        }
+        remember_log(last_level, line);
+    }
+}
 
-    match proc.child.try_wait() {
+/// 直近の Lavalink ログのうち `min_level` 以上の重要度のものを、新しいものから最大 `limit` 件（古い順に並べて）返す。
+pub fn recent_logs(min_level: tracing::Level, limit: usize) -> Vec<LavalinkLogLine> {
+    let Ok(logs) = RECENT_LOGS.lock() else {
+        return Vec::new();
+    };
+    // tracing::Level は重要度が高いほど小さい値として比較される。
+    let mut out = logs
+        .iter()
+        .rev()
+        .filter(|l| l.level <= min_level)
+        .take(limit)
+        .cloned()
+        .collect::<Vec<_>>();
+    out.reverse();
+    out
+}
+
+/// `/v4/info` が成功するまでポーリングする。
+async fn wait_until_ready(node: Option<&LavalinkNodeSettings>, max_wait: Duration) -> bool {
+    let Some(node) = node else {
+        return false;
+    };
+    let endpoint = format!("{}/v4/info", node.base_url.trim().trim_end_matches('/'));
+    let password = node
+        .password
+        .as_deref()
+        .map(str::trim)
+        .filter(|p| !p.is_empty());
+    let started = Instant::now();
+
+    while started.elapsed() < max_wait {
+        let mut req = get_http_client().get(&endpoint);
+        if let Some(password) = password {
+            req = req.header("Authorization", password);
+        }
+        if let Ok(Ok(resp)) = timeout(READY_POLL_INTERVAL * 4, req.send()).await {
+            if resp.status().is_success() {
+                tracing::info!(took_ms = started.elapsed().as_millis(), "Lavalink is ready");
+                return true;
+            }
+        }
+        tokio::time::sleep(READY_POLL_INTERVAL).await;
+    }
+
+    tracing::warn!(url = %endpoint, wait_secs = max_wait.as_secs(), "Lavalink did not become ready in time");
+    false
+}
+
+async fn stop_child(running: &mut RunningChild) {
+    match running.child.try_wait() {
         Ok(Some(status)) => {
             tracing::info!(status = %status, "Lavalink process already exited");
         }
         Ok(None) => {
             tracing::info!("stopping Lavalink process");
-            let _ = proc.child.start_kill();
-            match timeout(Duration::from_secs(5), proc.child.wait()).await {
+            let _ = running.child.start_kill();
+            match timeout(Duration::from_secs(5), running.child.wait()).await {
                 Ok(Ok(status)) => tracing::info!(status = %status, "Lavalink process stopped"),
                 Ok(Err(err)) => tracing::warn!(error = %err, "failed waiting Lavalink process"),
                 Err(_) => tracing::warn!("timeout while waiting Lavalink process to stop"),
@@ -168,12 +356,110 @@ pub async fn shutdown_lavalink(mut proc: Option<LavalinkProcess>) {
             tracing::warn!(error = %err, "failed to inspect Lavalink process status");
         }
     }
+    running.abort_readers();
+}
+
+async fn supervise(
+    spec: LaunchSpec,
+    mut running: RunningChild,
+    local_node: Option<LavalinkNodeSettings>,
+    ready_wait: Duration,
+    mut stop_rx: watch::Receiver<bool>,
+    restart_tx: watch::Sender<u64>,
+) {
+    let mut backoff = RESTART_BACKOFF_MIN;
+    loop {
+        tokio::select! {
+            status = running.child.wait() => {
+                running.abort_readers();
+                match status {
+                    Ok(status) => tracing::error!(status = %status, "Lavalink process exited unexpectedly"),
+                    Err(err) => tracing::error!(error = %err, "failed waiting Lavalink process"),
+                }
+                if running.started_at.elapsed() >= STABLE_RUN {
+                    backoff = RESTART_BACKOFF_MIN;
+                }
+            }
+            _ = stop_rx.changed() => {
+                stop_child(&mut running).await;
+                return;
+            }
+        }
+
+        // 再起動に成功するまでバックオフしながら繰り返す。
+        loop {
+            tracing::info!(
+                backoff_secs = backoff.as_secs(),
+                "restarting Lavalink after backoff"
+            );
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = stop_rx.changed() => return,
+            }
+            backoff = (backoff * 2).min(RESTART_BACKOFF_MAX);
 
-    proc.stdout_task.abort();
-    proc.stderr_task.abort();
+            let Some(next) = spawn_child(&spec) else {
+                continue;
+            };
+            running = next;
+            if wait_until_ready(local_node.as_ref(), ready_wait).await {
+                restart_tx.send_modify(|generation| *generation += 1);
+            }
+            break;
+        }
+    }
+}
+
+pub async fn spawn_lavalink(cfg: Option<&LavalinkSettings>) -> Option<LavalinkProcess> {
+    let Some(cfg) = cfg else {
+        return None;
+    };
+    if !cfg.enabled {
+        return None;
+    }
+    if !cfg.auto_start {
+        tracing::info!("lavalink auto-start disabled by config");
+        return None;
+    }
+
+    let spec = launch_spec(cfg)?;
+    let running = spawn_child(&spec)?;
+    let local_node = cfg.node_list().into_iter().next();
+    let ready_wait = Duration::from_secs(cfg.startup_timeout_secs());
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let (restart_tx, restarts) = watch::channel(0u64);
+    let task = tokio::spawn(supervise(
+        spec,
+        running,
+        local_node.clone(),
+        ready_wait,
+        stop_rx,
+        restart_tx,
+    ));
+
+    wait_until_ready(local_node.as_ref(), ready_wait).await;
+
+    Some(LavalinkProcess {
+        stop_tx,
+        restarts,
+        task,
+    })
+}
+
+pub async fn shutdown_lavalink(mut proc: Option<LavalinkProcess>) {
+    let Some(proc) = proc.take() else {
+        return;
+    };
+
+    let _ = proc.stop_tx.send(true);
+    if timeout(Duration::from_secs(10), proc.task).await.is_err() {
+        tracing::warn!("timeout while waiting Lavalink supervisor to stop");
+    }
 }
 
 /// Probe Lavalink REST API once on startup to verify configuration.
+/// 複数ノードが設定されている場合は各ノードの状態を個別に報告する。
 pub async fn probe_lavalink(cfg: Option<&LavalinkSettings>) {
     let Some(cfg) = cfg else {
         tracing::info!("lavalink config not found; running in songbird mode");
@@ -185,19 +471,40 @@ pub async fn probe_lavalink(cfg: Option<&LavalinkSettings>) {
         return;
     }
 
-    let Some(base_url) = cfg.base_url.as_deref().map(str::trim) else {
+    let nodes = cfg.node_list();
+    if nodes.is_empty() {
         tracing::warn!("lavalink is enabled but base_url is missing");
         return;
-    };
+    }
+
+    let timeout_secs = cfg.timeout_secs.unwrap_or(5).clamp(1, 30);
+    let mut healthy = 0usize;
+    for node in &nodes {
+        if let Some(info) = probe_node(node, timeout_secs).await {
+            let plugins = info
+                .plugins
+                .iter()
+                .map(|p| p.name.clone())
+                .collect::<Vec<_>>();
+            search_source::record_capabilities(&info.source_managers, &plugins);
+            healthy += 1;
+        }
+    }
+    tracing::info!(healthy, total = nodes.len(), "Lavalink node probe finished");
+}
+
+async fn probe_node(node: &LavalinkNodeSettings, timeout_secs: u64) -> Option<LavalinkInfo> {
+    let base_url = node.base_url.trim();
+    let label = node.label();
+    let region = node.region.as_deref().unwrap_or("-");
     if base_url.is_empty() {
-        tracing::warn!("lavalink is enabled but base_url is empty");
-        return;
+        tracing::warn!(node = %label, "lavalink node base_url is empty");
+        return None;
     }
 
     let endpoint = format!("{}/v4/info", base_url.trim_end_matches('/'));
-    let timeout_secs = cfg.timeout_secs.unwrap_or(5).clamp(1, 30);
     let mut req = get_http_client().get(&endpoint);
-    if let Some(password) = cfg.password.as_deref().map(str::trim) {
+    if let Some(password) = node.password.as_deref().map(str::trim) {
         if !password.is_empty() {
             req = req.header("Authorization", password);
         }
@@ -206,12 +513,12 @@ pub async fn probe_lavalink(cfg: Option<&LavalinkSettings>) {
     let response = match timeout(Duration::from_secs(timeout_secs), req.send()).await {
         Ok(Ok(resp)) => resp,
         Ok(Err(err)) => {
-            tracing::warn!(url = %endpoint, error = %err, "failed to connect to Lavalink");
-            return;
+            tracing::warn!(node = %label, region, url = %endpoint, error = %err, "failed to connect to Lavalink");
+            return None;
         }
         Err(_) => {
-            tracing::warn!(url = %endpoint, timeout_secs, "Lavalink probe timed out");
-            return;
+            tracing::warn!(node = %label, region, url = %endpoint, timeout_secs, "Lavalink probe timed out");
+            return None;
         }
     };
 
@@ -222,19 +529,22 @@ pub async fn probe_lavalink(cfg: Option<&LavalinkSettings>) {
             .await
             .unwrap_or_else(|_| "<failed to read response body>".to_string());
         tracing::warn!(
+            node = %label,
+            region,
             url = %endpoint,
             status = %status,
             body = %body.chars().take(200).collect::<String>(),
             "Lavalink probe failed"
         );
-        return;
+        return None;
     }
 
     match response.json::<LavalinkInfo>().await {
         Ok(info) => {
             let version = info
                 .version
-                .and_then(|v| v.semver)
+                .as_ref()
+                .and_then(|v| v.semver.clone())
                 .unwrap_or_else(|| "unknown".to_string());
             let plugin_names = info
                 .plugins
@@ -242,14 +552,18 @@ pub async fn probe_lavalink(cfg: Option<&LavalinkSettings>) {
                 .map(|p| p.name.as_str())
                 .collect::<Vec<_>>();
             tracing::info!(
+                node = %label,
+                region,
                 version = %version,
                 plugins = ?plugin_names,
+                sources = ?info.source_managers,
                 "Lavalink probe succeeded"
             );
-            tracing::info!("note: playback path is still songbird until migration is completed");
+            Some(info)
         }
         Err(err) => {
-            tracing::warn!(error = %err, "Lavalink /v4/info response parse failed");
+            tracing::warn!(node = %label, region, error = %err, "Lavalink /v4/info response parse failed");
+            None
         }
     }
 }
@@ -9,27 +9,35 @@ use lavalink_rs::{
     model::{
         BoxFuture,
         client::NodeDistributionStrategy,
-        events::{Events, TrackEnd, TrackStart},
-        http::UpdatePlayer,
-        search::SearchEngines,
+        events::{Events, PlayerUpdate, TrackEnd, TrackStart},
+        http::{UpdatePlayer, UpdatePlayerTrack},
         track::{Track as LavalinkTrack, TrackData, TrackLoadData},
     },
     node::NodeBuilder,
 };
-use poise::serenity_prelude::{Colour, EditMessage, GuildId, Http, UserId};
-use songbird::{ConnectionInfo as SongbirdConnectionInfo, tracks::PlayMode};
+use poise::serenity_prelude::{Cache, Colour, EditMessage, GuildId, Http, UserId};
+use songbird::{ConnectionInfo as SongbirdConnectionInfo, Songbird, tracks::PlayMode};
 use url::Url;
 
 use crate::util::{
     alias::Context,
+    autoplay, crossfade, filters, idle,
     music_ui::{control_components, track_embed},
-    player::PlaybackControlResult,
+    panel,
+    player::{PlaybackControlResult, SeekResult, SeekTarget},
+    prefetch,
     queue::MusicQueue,
     repeat::RepeatMode,
+    search_source::SearchSource,
+    settings::SettingsStore,
+    storage::MusicStore,
     track::TrackRequest,
-    types::{HistoryMap, LavalinkPlayingMap, NowPlayingMap, TransitionFlags},
+    types::{
+        AutoPaused, FadeGenerations, HistoryMap, IdleTimers, LavalinkPlayingMap, NowPlayingMap,
+        PanelUpdaters, PositionMap, PrefetchMap, TransitionFlags, VoiceChannelMap, VoteMap,
+    },
 };
-use crate::{Error, LavalinkSettings};
+use crate::{Error, LavalinkNodeSettings, LavalinkSettings};
 
 #[derive(Clone)]
 pub struct LavalinkRuntimeData {
@@ -38,7 +46,19 @@ pub struct LavalinkRuntimeData {
     pub history: HistoryMap,
     pub now_playing: NowPlayingMap,
     pub lavalink_playing: LavalinkPlayingMap,
+    pub voice_channels: VoiceChannelMap,
+    pub positions: PositionMap,
+    pub prefetched: PrefetchMap,
+    pub fades: FadeGenerations,
+    pub panel_updaters: PanelUpdaters,
+    pub idle_timers: IdleTimers,
+    pub auto_paused: AutoPaused,
+    pub votes: VoteMap,
+    pub store: Arc<MusicStore>,
+    pub settings: Arc<SettingsStore>,
+    pub songbird: Arc<Songbird>,
     pub http: Arc<Http>,
+    pub cache: Arc<Cache>,
 }
 
 pub struct PlayNextResult {
@@ -46,15 +66,31 @@ pub struct PlayNextResult {
     pub skipped: usize,
     pub remaining: usize,
     pub last_error: Option<String>,
+    /// 自動再生で追加した曲を再生し始めたか
+    pub autoplayed: bool,
 }
 
-const HISTORY_MAX: usize = 50;
+/// クライアントに紐づく永続化ストアへ変更を通知する。
+fn mark_dirty(lavalink: &LavalinkClient, guild_id: GuildId) {
+    if let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() {
+        runtime.store.mark_dirty(guild_id);
+    }
+}
 
 fn first_track_from_load(load: LavalinkTrack) -> Result<Option<TrackData>, Error> {
     match load.data {
         Some(TrackLoadData::Track(track)) => Ok(Some(track)),
         Some(TrackLoadData::Search(tracks)) => Ok(tracks.into_iter().next()),
-        Some(TrackLoadData::Playlist(playlist)) => Ok(playlist.tracks.into_iter().next()),
+        Some(TrackLoadData::Playlist(playlist)) => {
+            // プレイリストはキューに入れる前に展開するので、ここでは 1 曲分だけ使う
+            if playlist.tracks.len() > 1 {
+                tracing::debug!(
+                    tracks = playlist.tracks.len(),
+                    "playlist returned while resolving a single track; using the first"
+                );
+            }
+            Ok(playlist.tracks.into_iter().next())
+        }
         Some(TrackLoadData::Error(err)) => Err(Error::from(format!(
             "Lavalink track load failed: {}",
             err.message
@@ -63,7 +99,7 @@ fn first_track_from_load(load: LavalinkTrack) -> Result<Option<TrackData>, Error
     }
 }
 
-async fn resolve_track(
+pub(crate) async fn resolve_track(
     lavalink: &LavalinkClient,
     guild_id: GuildId,
     identifier: &str,
@@ -78,22 +114,32 @@ async fn resolve_track(
     }
 
     if Url::parse(identifier).is_err() {
-        let query = SearchEngines::YouTube
-            .to_query(identifier)
-            .map_err(|e| Error::from(format!("Lavalink search query error: {e}")))?;
-        let load = lavalink
-            .load_tracks(guild_id, &query)
-            .await
-            .map_err(|e| Error::from(format!("Lavalink search load_tracks error: {e}")))?;
-        if let Some(track) = first_track_from_load(load)? {
-            return Ok(track);
+        // サーバー既定のソースで見つからなければ YouTube・SoundCloud も探す
+        let preferred = lavalink
+            .data::<LavalinkRuntimeData>()
+            .map(|runtime| runtime.settings.get(guild_id).search_source)
+            .unwrap_or_default();
+        let mut sources = vec![preferred];
+        for fallback in [SearchSource::YouTube, SearchSource::SoundCloud] {
+            if !sources.contains(&fallback) {
+                sources.push(fallback);
+            }
+        }
+        for source in sources.into_iter().filter(|s| s.is_available()) {
+            let load = lavalink
+                .load_tracks(guild_id, &source.query(identifier))
+                .await
+                .map_err(|e| Error::from(format!("Lavalink search load_tracks error: {e}")))?;
+            if let Some(track) = first_track_from_load(load)? {
+                return Ok(track);
+            }
         }
     }
 
     Err(Error::from("Lavalink did not return a playable track"))
 }
 
-fn apply_track_metadata(req: &mut TrackRequest, track: &TrackData) {
+pub(crate) fn apply_track_metadata(req: &mut TrackRequest, track: &TrackData) {
     req.meta.title = Some(track.info.title.clone());
     if !track.info.author.trim().is_empty() {
         req.meta.artist = Some(track.info.author.clone());
@@ -125,6 +171,27 @@ fn lavalink_track_start(
     })
 }
 
+fn lavalink_player_update(
+    client: LavalinkClient,
+    _session_id: String,
+    event: &PlayerUpdate,
+) -> BoxFuture<'static, ()> {
+    let event = event.clone();
+    Box::pin(async move {
+        let Ok(runtime) = client.data::<LavalinkRuntimeData>() else {
+            return;
+        };
+        let guild_id = GuildId::new(event.guild_id.0);
+        if !runtime.lavalink_playing.contains_key(&guild_id) {
+            return;
+        }
+        // 再起動後に続きから再開できるよう、定期的な位置更新を保存しておく。
+        runtime.positions.insert(guild_id, event.state.position);
+        runtime.store.mark_dirty(guild_id);
+        prefetch::prefetch_if_near_end(Arc::new(client), guild_id, event.state.position);
+    })
+}
+
 fn lavalink_track_end(
     client: LavalinkClient,
     _session_id: String,
@@ -158,35 +225,35 @@ async fn handle_track_end(client: LavalinkClient, event: TrackEnd) {
         .lavalink_playing
         .remove(&guild_id)
         .map(|(_, req)| req);
+    runtime.votes.remove(&guild_id);
 
+    let default_repeat = runtime.settings.get(guild_id).default_repeat;
     if let Some(mut q) = runtime.queues.get_mut(&guild_id) {
         if let Some(req) = finished.clone() {
-            match q.config.repeat_mode {
+            match q.config.repeat_mode_or(default_repeat) {
                 RepeatMode::Track => q.push_front(req),
                 RepeatMode::Queue => q.push_back(req),
                 RepeatMode::Off => {}
             }
         }
     }
+    runtime.store.mark_dirty(guild_id);
 
-    let result = play_next_from_queue_lavalink(
-        guild_id,
-        Arc::new(client.clone()),
-        runtime.queues.clone(),
-        runtime.lavalink_playing.clone(),
-        runtime.history.clone(),
-        3,
-    )
-    .await;
+    // 停止操作で終わった場合は再生中の曲が外されているので自動再生しない
+    let result = play_next_or_autoplay(guild_id, Arc::new(client.clone()), finished).await;
 
     let Ok(result) = result else {
         tracing::warn!(guild = %guild_id, "failed to start next Lavalink track");
         return;
     };
 
+    if result.started.is_none() {
+        idle::start_timer(Arc::new(client.clone()), guild_id);
+    }
+
     if let Some((channel_id, message_id)) = runtime.now_playing.get(&guild_id).map(|e| *e.value()) {
         if let Some(started) = result.started {
-            let info = if result.skipped > 0 {
+            let mut info = if result.skipped > 0 {
                 format!(
                     "再生失敗 {} 件をスキップ / キュー残り {} 件",
                     result.skipped, result.remaining
@@ -194,6 +261,9 @@ async fn handle_track_end(client: LavalinkClient, event: TrackEnd) {
             } else {
                 format!("キュー残り {} 件", result.remaining)
             };
+            if result.autoplayed {
+                info.insert_str(0, "📻 自動再生 / ");
+            }
             let embed = track_embed(
                 "🎵 再生中",
                 Some(&started),
@@ -236,58 +306,82 @@ fn build_events() -> Events {
     Events {
         track_start: Some(lavalink_track_start),
         track_end: Some(lavalink_track_end),
+        player_update: Some(lavalink_player_update),
         ..Default::default()
     }
 }
 
-pub async fn build_lavalink_client(
-    settings: &LavalinkSettings,
-    user_id: UserId,
-    runtime_data: LavalinkRuntimeData,
-) -> Result<Arc<LavalinkClient>, Error> {
-    let base_url = settings
-        .base_url
-        .as_deref()
-        .map(str::trim)
-        .ok_or_else(|| Error::from("lavalink base_url is missing"))?;
+fn node_builder(node: &LavalinkNodeSettings, user_id: UserId) -> Result<NodeBuilder, Error> {
+    let base_url = node.base_url.trim();
     if base_url.is_empty() {
-        return Err(Error::from("lavalink base_url is empty"));
+        return Err(Error::from(format!(
+            "lavalink node `{}` base_url is empty",
+            node.label()
+        )));
     }
 
-    let parsed =
-        Url::parse(base_url).map_err(|e| Error::from(format!("invalid lavalink base_url: {e}")))?;
+    let parsed = Url::parse(base_url).map_err(|e| {
+        Error::from(format!(
+            "invalid lavalink base_url for node `{}`: {e}",
+            node.label()
+        ))
+    })?;
     let host = parsed
         .host_str()
         .ok_or_else(|| Error::from("lavalink base_url host is missing"))?;
     let port = parsed.port_or_known_default().unwrap_or(2333);
     let hostname = format!("{host}:{port}");
-    let is_ssl = parsed.scheme().eq_ignore_ascii_case("https");
-    let password = settings
+    let is_ssl = node
+        .ssl
+        .unwrap_or_else(|| parsed.scheme().eq_ignore_ascii_case("https"));
+    let password = node
         .password
         .clone()
         .unwrap_or_else(|| "youshallnotpass".to_string());
 
     tracing::info!(
+        node = %node.label(),
         host = %hostname,
         ssl = is_ssl,
-        "initializing Lavalink client"
+        region = node.region.as_deref().unwrap_or("-"),
+        "registering Lavalink node"
     );
 
-    let node = NodeBuilder {
+    Ok(NodeBuilder {
         hostname,
         is_ssl,
         password,
         user_id: user_id.into(),
         ..Default::default()
+    })
+}
+
+pub async fn build_lavalink_client(
+    settings: &LavalinkSettings,
+    user_id: UserId,
+    runtime_data: LavalinkRuntimeData,
+) -> Result<Arc<LavalinkClient>, Error> {
+    let node_settings = settings.node_list();
+    if node_settings.is_empty() {
+        return Err(Error::from("lavalink base_url is missing"));
+    }
+    let nodes = node_settings
+        .iter()
+        .map(|n| node_builder(n, user_id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 複数ノード時は設定順を優先度とし、停止中のノードを避けて割り当てる。
+    let strategy = if nodes.len() > 1 {
+        NodeDistributionStrategy::main_fallback()
+    } else {
+        NodeDistributionStrategy::sharded()
     };
 
-    let client = LavalinkClient::new_with_data(
-        build_events(),
-        vec![node],
-        NodeDistributionStrategy::sharded(),
-        Arc::new(runtime_data),
-    )
-    .await;
+    tracing::info!(nodes = nodes.len(), "initializing Lavalink client");
+
+    let client =
+        LavalinkClient::new_with_data(build_events(), nodes, strategy, Arc::new(runtime_data))
+            .await;
 
     Ok(Arc::new(client))
 }
@@ -400,51 +494,208 @@ pub async fn resume_current_lavalink(
     Ok(PlaybackControlResult::Changed(req))
 }
 
+/// 再生中のトラック内を移動する。曲の長さを超える位置は終端の直前に丸める。
+pub async fn seek_current_lavalink(
+    lavalink: &Arc<LavalinkClient>,
+    guild_id: GuildId,
+    playing: &LavalinkPlayingMap,
+    target: SeekTarget,
+) -> Result<SeekResult, Error> {
+    let Some(req) = playing.get(&guild_id).map(|e| e.value().clone()) else {
+        return Ok(SeekResult::Missing);
+    };
+    let Some(player) = lavalink.get_player_context(guild_id) else {
+        return Ok(SeekResult::Missing);
+    };
+    let state = player
+        .get_player()
+        .await
+        .map_err(|e| Error::from(format!("failed to get Lavalink player state: {e}")))?;
+    let Some(track) = state.track else {
+        return Ok(SeekResult::Missing);
+    };
+    if !track.info.is_seekable || track.info.is_stream {
+        return Ok(SeekResult::NotSeekable);
+    }
+
+    let length = Duration::from_millis(track.info.length);
+    let current = Duration::from_millis(state.state.position);
+    let position = match target {
+        SeekTarget::Absolute(p) => p,
+        SeekTarget::Forward(d) => current + d,
+        SeekTarget::Backward(d) => current.saturating_sub(d),
+    }
+    .min(length.saturating_sub(Duration::from_secs(1)));
+
+    player
+        .set_position(position)
+        .await
+        .map_err(|e| Error::from(format!("failed to seek Lavalink player: {e}")))?;
+
+    if let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() {
+        runtime
+            .positions
+            .insert(guild_id, position.as_millis() as u64);
+    }
+    mark_dirty(lavalink, guild_id);
+    crossfade::restart_after_seek(lavalink.clone(), guild_id, &track);
+
+    Ok(SeekResult::Moved(req, position, length))
+}
+
 pub async fn stop_and_clear_lavalink(ctx: &Context<'_>, guild_id: GuildId) -> Result<(), Error> {
     if let Some(lavalink) = &ctx.data().lavalink {
         if let Some(player) = lavalink.get_player_context(guild_id) {
             let _ = player.stop_now().await;
         }
+        idle::start_timer(lavalink.clone(), guild_id);
     }
 
     ctx.data().queues.remove(&guild_id);
     ctx.data().lavalink_playing.remove(&guild_id);
     ctx.data().history.remove(&guild_id);
     ctx.data().now_playing.remove(&guild_id);
+    ctx.data().prefetched.remove(&guild_id);
+    ctx.data().store.mark_dirty(guild_id);
 
     Ok(())
 }
 
 pub async fn play_track_req_lavalink(
+    guild_id: GuildId,
+    lavalink: Arc<LavalinkClient>,
+    playing: LavalinkPlayingMap,
+    history: HistoryMap,
+    tr: TrackRequest,
+) -> Result<TrackRequest, Error> {
+    play_track_req_lavalink_at(guild_id, lavalink, playing, history, tr, None).await
+}
+
+/// `start_at` を指定すると、その位置から再生を開始する（シーク不可のトラックでは先頭から）。
+pub async fn play_track_req_lavalink_at(
     guild_id: GuildId,
     lavalink: Arc<LavalinkClient>,
     playing: LavalinkPlayingMap,
     history: HistoryMap,
     mut tr: TrackRequest,
+    start_at: Option<Duration>,
 ) -> Result<TrackRequest, Error> {
-    let track_data = resolve_track(&lavalink, guild_id, &tr.url).await?;
+    let track_data = match prefetch::take_prefetched(&lavalink, guild_id, &tr.url) {
+        Some(track) => track,
+        None => resolve_track(&lavalink, guild_id, &tr.url).await?,
+    };
     apply_track_metadata(&mut tr, &track_data);
 
-    let player = lavalink
-        .get_player_context(guild_id)
-        .ok_or_else(|| Error::from("Lavalink player is not connected to this guild"))?;
-    player
-        .play_now(&track_data)
+    let runtime = lavalink
+        .data::<LavalinkRuntimeData>()
+        .map_err(|e| Error::from(format!("failed to fetch lavalink runtime data: {e}")))?;
+    if lavalink.get_player_context(guild_id).is_none() {
+        return Err(Error::from(
+            "Lavalink player is not connected to this guild",
+        ));
+    }
+    let fade = crossfade::begin_transition(&lavalink, guild_id);
+    let start_at = start_at.filter(|p| !p.is_zero() && track_data.info.is_seekable);
+    // 音量とフィルターはトラックと一緒に毎回送り、プレイヤーの作り直し後も維持されるようにする。
+    lavalink
+        .update_player(
+            guild_id,
+            &UpdatePlayer {
+                track: Some(UpdatePlayerTrack {
+                    encoded: Some(track_data.encoded.clone()),
+                    ..Default::default()
+                }),
+                position: start_at.map(|p| p.as_millis() as u64),
+                paused: Some(false),
+                volume: Some(filters::guild_volume(
+                    &runtime.queues,
+                    &runtime.settings,
+                    guild_id,
+                )),
+                filters: Some(filters::guild_filters(
+                    &runtime.queues,
+                    guild_id,
+                    fade.map(|_| 0.0),
+                )),
+                ..Default::default()
+            },
+            false,
+        )
         .await
         .map_err(|e| Error::from(format!("failed to start Lavalink playback: {e}")))?;
 
     playing.insert(guild_id, tr.clone());
+    runtime.votes.remove(&guild_id);
+    let start_ms = start_at.map(|p| p.as_millis() as u64).unwrap_or(0);
+    runtime.positions.insert(guild_id, start_ms);
+    let history_limit = runtime.settings.get(guild_id).history_limit.max(1);
     {
         let mut h = history.entry(guild_id).or_default();
         h.push_back(tr.clone());
-        while h.len() > HISTORY_MAX {
+        while h.len() > history_limit {
             h.pop_front();
         }
     }
+    mark_dirty(&lavalink, guild_id);
+
+    if let Some(fade) = fade {
+        crossfade::spawn_fades(lavalink.clone(), guild_id, fade, &track_data, true);
+    }
+    prefetch::spawn_prefetch(lavalink.clone(), guild_id);
+    panel::ensure_updater(lavalink, guild_id);
 
     Ok(tr)
 }
 
+/// キューから次の曲を再生する。キューが尽きていて自動再生が有効なら、
+/// `seed`（直前の曲）に関連する曲をキューへ足してからもう一度試す。
+pub async fn play_next_or_autoplay(
+    guild_id: GuildId,
+    lavalink: Arc<LavalinkClient>,
+    seed: Option<TrackRequest>,
+) -> Result<PlayNextResult, Error> {
+    let runtime = lavalink
+        .data::<LavalinkRuntimeData>()
+        .map_err(|e| Error::from(format!("failed to fetch lavalink runtime data: {e}")))?;
+    let result = play_next_from_queue_lavalink(
+        guild_id,
+        lavalink.clone(),
+        runtime.queues.clone(),
+        runtime.lavalink_playing.clone(),
+        runtime.history.clone(),
+        3,
+    )
+    .await?;
+
+    let autoplay = runtime
+        .queues
+        .get(&guild_id)
+        .is_some_and(|q| q.config.autoplay);
+    let Some(seed) = seed.filter(|_| result.started.is_none() && autoplay) else {
+        return Ok(result);
+    };
+    match autoplay::enqueue_related(&lavalink, &runtime, guild_id, &seed).await {
+        Ok(added) if added > 0 => {
+            let mut next = play_next_from_queue_lavalink(
+                guild_id,
+                lavalink.clone(),
+                runtime.queues.clone(),
+                runtime.lavalink_playing.clone(),
+                runtime.history.clone(),
+                3,
+            )
+            .await?;
+            next.autoplayed = next.started.is_some();
+            Ok(next)
+        }
+        Ok(_) => Ok(result),
+        Err(err) => {
+            tracing::warn!(guild = %guild_id, error = %err, "autoplay failed to find related tracks");
+            Ok(result)
+        }
+    }
+}
+
 pub async fn play_next_from_queue_lavalink(
     guild_id: GuildId,
     lavalink: Arc<LavalinkClient>,
@@ -473,6 +724,7 @@ pub async fn play_next_from_queue_lavalink(
                 skipped,
                 remaining,
                 last_error,
+                autoplayed: false,
             });
         };
 
@@ -491,11 +743,13 @@ pub async fn play_next_from_queue_lavalink(
                     skipped,
                     remaining,
                     last_error,
+                    autoplayed: false,
                 });
             }
             Err(err) => {
                 last_error = Some(err.to_string());
                 skipped += 1;
+                mark_dirty(&lavalink, guild_id);
             }
         }
     }
@@ -505,5 +759,6 @@ pub async fn play_next_from_queue_lavalink(
         skipped,
         remaining,
         last_error,
+        autoplayed: false,
     })
 }
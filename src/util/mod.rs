@@ -1,13 +1,29 @@
 pub mod alias;
+pub mod autoplay;
 pub mod capstone;
 pub mod config;
+pub mod crossfade;
+pub mod failover;
+pub mod filters;
+pub mod idle;
 pub mod lavalink;
 pub mod lavalink_player;
+pub mod music_links;
 pub mod music_ui;
+pub mod panel;
+pub mod permissions;
 pub mod player;
 pub mod playlist;
+pub mod prefetch;
 pub mod queue;
 pub mod repeat;
+pub mod resume;
+pub mod saved_playlists;
+pub mod search_source;
+pub mod settings;
+pub mod storage;
+pub mod suggest;
 pub mod track;
 pub mod types;
+pub mod vote;
 pub mod ytdlp;
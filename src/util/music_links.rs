@@ -0,0 +1,563 @@
+use std::time::Duration;
+
+use lavalink_rs::client::LavalinkClient;
+use poise::serenity_prelude::{GuildId, UserId};
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    GLOBAL_CONFIG, get_http_client,
+    util::{
+        alias::Error,
+        playlist::load_with_lavalink,
+        search_source::{self, SearchSource},
+        track::TrackRequest,
+    },
+};
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// 埋め込みページ取得用（既定の UA だと簡易ページが返ることがある）
+const BROWSER_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Safari/537.36";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MusicService {
+    Spotify,
+    AppleMusic,
+    Deezer,
+}
+
+impl MusicService {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Spotify => "Spotify",
+            Self::AppleMusic => "Apple Music",
+            Self::Deezer => "Deezer",
+        }
+    }
+
+    fn search_source(self) -> SearchSource {
+        match self {
+            Self::Spotify => SearchSource::Spotify,
+            Self::AppleMusic => SearchSource::AppleMusic,
+            Self::Deezer => SearchSource::Deezer,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    Track,
+    Album,
+    Playlist,
+    /// 短縮 URL（リダイレクト先を見るまで種類が分からない）
+    Short,
+}
+
+/// Spotify / Apple Music / Deezer のリンク。
+#[derive(Clone, Debug)]
+pub struct MusicLink {
+    pub service: MusicService,
+    pub kind: LinkKind,
+    pub id: String,
+    pub url: String,
+}
+
+/// 入力が対応サービスのリンクなら種類と ID を取り出す。
+pub fn detect(raw: &str) -> Option<MusicLink> {
+    let raw = raw.trim();
+    // spotify:track:xxxx 形式
+    if let Some(rest) = raw.strip_prefix("spotify:") {
+        let (kind, id) = rest.split_once(':')?;
+        return spotify_link(kind, id);
+    }
+    let url = Url::parse(raw).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+    let segs: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|p| !p.is_empty()).collect())
+        .unwrap_or_default();
+    let short = |service| {
+        Some(MusicLink {
+            service,
+            kind: LinkKind::Short,
+            id: String::new(),
+            url: raw.to_string(),
+        })
+    };
+    match host {
+        "open.spotify.com" | "play.spotify.com" => {
+            // /intl-ja/track/xxxx のようなロケール付きも受け付ける
+            let segs: Vec<&str> = segs
+                .into_iter()
+                .filter(|s| !s.starts_with("intl-") && *s != "embed")
+                .collect();
+            spotify_link(segs.first()?, segs.get(1)?)
+        }
+        "spotify.link" => short(MusicService::Spotify),
+        "music.apple.com" | "itunes.apple.com" => {
+            // /jp/album/<名前>/<ID>?i=<曲ID> は曲、/jp/song/<名前>/<ID> も曲
+            let kind_at = segs
+                .iter()
+                .position(|s| matches!(*s, "album" | "song" | "playlist"))?;
+            let id = segs.last()?.trim_start_matches("id").to_string();
+            let track_id = url
+                .query_pairs()
+                .find_map(|(k, v)| (k == "i").then(|| v.into_owned()));
+            let (kind, id) = match (segs[kind_at], track_id) {
+                ("album", Some(track)) => (LinkKind::Track, track),
+                ("album", None) => (LinkKind::Album, id),
+                ("song", _) => (LinkKind::Track, id),
+                _ => (LinkKind::Playlist, id),
+            };
+            Some(MusicLink {
+                service: MusicService::AppleMusic,
+                kind,
+                id,
+                url: raw.to_string(),
+            })
+        }
+        "deezer.com" => {
+            // /jp/track/xxxx のような言語付きも受け付ける
+            let kind_at = segs
+                .iter()
+                .position(|s| matches!(*s, "track" | "album" | "playlist"))?;
+            let kind = match segs[kind_at] {
+                "track" => LinkKind::Track,
+                "album" => LinkKind::Album,
+                _ => LinkKind::Playlist,
+            };
+            Some(MusicLink {
+                service: MusicService::Deezer,
+                kind,
+                id: segs.get(kind_at + 1)?.to_string(),
+                url: raw.to_string(),
+            })
+        }
+        "link.deezer.com" | "deezer.page.link" => short(MusicService::Deezer),
+        _ => None,
+    }
+}
+
+fn spotify_link(kind: &str, id: &str) -> Option<MusicLink> {
+    let kind = match kind {
+        "track" => LinkKind::Track,
+        "album" => LinkKind::Album,
+        "playlist" => LinkKind::Playlist,
+        _ => return None,
+    };
+    let id = id.trim();
+    if id.is_empty() {
+        return None;
+    }
+    let path = match kind {
+        LinkKind::Track => "track",
+        LinkKind::Album => "album",
+        _ => "playlist",
+    };
+    Some(MusicLink {
+        service: MusicService::Spotify,
+        kind,
+        id: id.to_string(),
+        url: format!("https://open.spotify.com/{path}/{id}"),
+    })
+}
+
+/// 1 曲分の情報（検索語の元になる）。
+struct LinkItem {
+    title: String,
+    artist: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl LinkItem {
+    fn into_request(self, requested_by: UserId) -> TrackRequest {
+        // 再生時に `resolve_track` が YouTube（見つからなければ SoundCloud）で検索する
+        let query = match &self.artist {
+            Some(artist) => format!("{artist} - {}", self.title),
+            None => self.title.clone(),
+        };
+        let mut req = TrackRequest::new(query, requested_by);
+        req.meta.title = Some(self.title);
+        req.meta.artist = self.artist;
+        req.meta.duration = self.duration;
+        req
+    }
+}
+
+/// リンクを再生できるリクエストに変換する。アルバム・プレイリストは最大 `limit` 曲に展開する。
+///
+/// Lavalink に LavaSrc プラグインが入っている設定ならそちらで読み込み、
+/// 使えない場合は公開されている埋め込みデータや API から曲名を取り出して検索語にする。
+#[tracing::instrument(
+    name = "music_links::resolve",
+    level = "info",
+    skip_all,
+    fields(service = link.service.label(), kind = ?link.kind, url = %link.url)
+)]
+pub async fn resolve(
+    lavalink: Option<&LavalinkClient>,
+    guild_id: GuildId,
+    link: &MusicLink,
+    limit: usize,
+    requested_by: UserId,
+) -> Result<Vec<TrackRequest>, Error> {
+    let limit = limit.max(1);
+    let expanded;
+    let link = if link.kind == LinkKind::Short {
+        expanded = expand_short_link(link).await?;
+        &expanded
+    } else {
+        link
+    };
+
+    if let Some(lavalink) = lavalink.filter(|_| lavasrc_enabled(link.service)) {
+        match load_with_lavalink(lavalink, guild_id, &link.url, limit, requested_by).await {
+            Ok(reqs) if !reqs.is_empty() => return Ok(reqs),
+            Ok(_) => tracing::info!("LavaSrc returned no tracks; falling back to public data"),
+            Err(err) => {
+                tracing::warn!(error = %err, "LavaSrc load failed; falling back to public data")
+            }
+        }
+    }
+
+    let items = match link.service {
+        MusicService::Spotify => spotify_items(link).await?,
+        MusicService::AppleMusic => apple_items(link).await?,
+        MusicService::Deezer => deezer_items(link).await?,
+    };
+    let reqs: Vec<TrackRequest> = items
+        .into_iter()
+        .filter(|item| !item.title.trim().is_empty())
+        .take(limit)
+        .map(|item| item.into_request(requested_by))
+        .collect();
+    if reqs.is_empty() {
+        return Err(Error::from(format!(
+            "{} のリンクから曲を取得できませんでした",
+            link.service.label()
+        )));
+    }
+    tracing::info!(items = reqs.len(), "music link resolved");
+    Ok(reqs)
+}
+
+/// LavaSrc でリンクを読み込めるか。起動時に `/v4/info` が取れていれば、
+/// プラグインとそのサービスのソースが有効かで決め、取れていなければ設定の `lavasrc` に従う。
+fn lavasrc_enabled(service: MusicService) -> bool {
+    match search_source::lavasrc_loaded() {
+        Some(loaded) => loaded && service.search_source().is_available(),
+        None => GLOBAL_CONFIG.lavalink.as_ref().is_some_and(|c| c.lavasrc),
+    }
+}
+
+async fn fetch(url: &str) -> Result<reqwest::Response, Error> {
+    let resp = tokio::time::timeout(
+        FETCH_TIMEOUT,
+        get_http_client()
+            .get(url)
+            .header("User-Agent", BROWSER_UA)
+            .header("Accept-Language", "ja,en;q=0.8")
+            .send(),
+    )
+    .await
+    .map_err(|_| Error::from("リンク先の取得がタイムアウトしました"))?
+    .map_err(|e| Error::from(format!("リンク先の取得に失敗しました: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(Error::from(format!(
+            "リンク先の取得に失敗しました (HTTP {})",
+            resp.status()
+        )));
+    }
+    Ok(resp)
+}
+
+pub(crate) async fn fetch_text(url: &str) -> Result<String, Error> {
+    fetch(url)
+        .await?
+        .text()
+        .await
+        .map_err(|e| Error::from(format!("リンク先の読み込みに失敗しました: {e}")))
+}
+
+async fn fetch_json(url: &str) -> Result<Value, Error> {
+    fetch(url)
+        .await?
+        .json::<Value>()
+        .await
+        .map_err(|e| Error::from(format!("リンク先の JSON 解析に失敗しました: {e}")))
+}
+
+/// 短縮 URL のリダイレクト先を調べて本来のリンクにする。
+async fn expand_short_link(link: &MusicLink) -> Result<MusicLink, Error> {
+    let resp = fetch(&link.url).await?;
+    let final_url = resp.url().to_string();
+    if let Some(expanded) = detect(&final_url).filter(|l| l.kind != LinkKind::Short) {
+        return Ok(expanded);
+    }
+    // リダイレクトせずページ内で転送するものは本文中の正規 URL を探す
+    let body = resp.text().await.unwrap_or_default();
+    find_canonical_url(&body)
+        .and_then(|u| detect(&u))
+        .filter(|l| l.kind != LinkKind::Short)
+        .ok_or_else(|| {
+            Error::from(format!(
+                "{} の短縮リンクを展開できませんでした",
+                link.service.label()
+            ))
+        })
+}
+
+fn find_canonical_url(html: &str) -> Option<String> {
+    ["\"og:url\" content=\"", "rel=\"canonical\" href=\""]
+        .iter()
+        .find_map(|marker| {
+            let start = html.find(marker)? + marker.len();
+            let end = html[start..].find('"')?;
+            Some(html[start..start + end].replace("&amp;", "&"))
+        })
+}
+
+/// Spotify の埋め込みページに含まれる `__NEXT_DATA__` から曲一覧を読む。
+async fn spotify_items(link: &MusicLink) -> Result<Vec<LinkItem>, Error> {
+    let kind = match link.kind {
+        LinkKind::Track => "track",
+        LinkKind::Album => "album",
+        _ => "playlist",
+    };
+    let html = fetch_text(&format!(
+        "https://open.spotify.com/embed/{kind}/{}",
+        link.id
+    ))
+    .await?;
+    let data = script_json(&html, "id=\"__NEXT_DATA__\"")
+        .ok_or_else(|| Error::from("Spotify の埋め込みデータが見つかりませんでした"))?;
+    let entity = data
+        .pointer("/props/pageProps/state/data/entity")
+        .ok_or_else(|| Error::from("Spotify の埋め込みデータの形式が想定と異なります"))?;
+
+    if let Some(list) = entity.get("trackList").and_then(Value::as_array) {
+        return Ok(list
+            .iter()
+            .filter_map(|t| {
+                Some(LinkItem {
+                    title: str_field(t, &["title", "name"])?,
+                    artist: str_field(t, &["subtitle"]),
+                    duration: t
+                        .get("duration")
+                        .and_then(Value::as_u64)
+                        .map(Duration::from_millis),
+                })
+            })
+            .collect());
+    }
+    let title = str_field(entity, &["title", "name"])
+        .ok_or_else(|| Error::from("Spotify の曲名を取得できませんでした"))?;
+    let artist = entity
+        .get("artists")
+        .and_then(Value::as_array)
+        .map(|artists| {
+            artists
+                .iter()
+                .filter_map(|a| a.get("name").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|a| !a.is_empty())
+        .or_else(|| str_field(entity, &["subtitle"]));
+    let duration = entity
+        .get("duration")
+        .and_then(Value::as_u64)
+        .map(Duration::from_millis);
+    Ok(vec![LinkItem {
+        title,
+        artist,
+        duration,
+    }])
+}
+
+/// Apple Music は曲・アルバムを iTunes Lookup API、プレイリストをページの JSON-LD から読む。
+async fn apple_items(link: &MusicLink) -> Result<Vec<LinkItem>, Error> {
+    if link.kind == LinkKind::Playlist {
+        let html = fetch_text(&link.url).await?;
+        return Ok(json_ld_items(&html));
+    }
+    let json = fetch_json(&format!(
+        "https://itunes.apple.com/lookup?id={}&entity=song&limit=200&country={}",
+        link.id,
+        apple_storefront(&link.url)
+    ))
+    .await?;
+    let results = json
+        .get("results")
+        .and_then(Value::as_array)
+        .ok_or_else(|| Error::from("Apple Music の応答に曲が含まれていませんでした"))?;
+    Ok(results
+        .iter()
+        .filter(|r| r.get("wrapperType").and_then(Value::as_str) == Some("track"))
+        .filter_map(|r| {
+            Some(LinkItem {
+                title: str_field(r, &["trackName"])?,
+                artist: str_field(r, &["artistName"]),
+                duration: r
+                    .get("trackTimeMillis")
+                    .and_then(Value::as_u64)
+                    .map(Duration::from_millis),
+            })
+        })
+        .collect())
+}
+
+/// `music.apple.com/jp/...` の国コード（無ければ us）。
+fn apple_storefront(raw: &str) -> String {
+    Url::parse(raw)
+        .ok()
+        .and_then(|u| {
+            u.path_segments()?
+                .next()
+                .filter(|s| s.len() == 2)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "us".to_string())
+}
+
+/// Deezer の公開 API から曲情報を読む。
+async fn deezer_items(link: &MusicLink) -> Result<Vec<LinkItem>, Error> {
+    let kind = match link.kind {
+        LinkKind::Track => "track",
+        LinkKind::Album => "album",
+        _ => "playlist",
+    };
+    let json = fetch_json(&format!("https://api.deezer.com/{kind}/{}", link.id)).await?;
+    if let Some(err) = json.pointer("/error/message").and_then(Value::as_str) {
+        return Err(Error::from(format!("Deezer API エラー: {err}")));
+    }
+    let item = |t: &Value| {
+        Some(LinkItem {
+            title: str_field(t, &["title"])?,
+            artist: t
+                .pointer("/artist/name")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            duration: t
+                .get("duration")
+                .and_then(Value::as_u64)
+                .map(Duration::from_secs),
+        })
+    };
+    if link.kind == LinkKind::Track {
+        return Ok(item(&json).into_iter().collect());
+    }
+    Ok(json
+        .pointer("/tracks/data")
+        .and_then(Value::as_array)
+        .map(|list| list.iter().filter_map(item).collect())
+        .unwrap_or_default())
+}
+
+/// `<script ...marker...>{json}</script>` の JSON を取り出す。
+fn script_json(html: &str, marker: &str) -> Option<Value> {
+    let at = html.find(marker)?;
+    let start = at + html[at..].find('>')? + 1;
+    let end = start + html[start..].find("</script>")?;
+    serde_json::from_str(html[start..end].trim()).ok()
+}
+
+/// ページ内の JSON-LD（MusicPlaylist / MusicAlbum / MusicRecording）から曲を集める。
+fn json_ld_items(html: &str) -> Vec<LinkItem> {
+    const MARKER: &str = "application/ld+json";
+    let mut items = Vec::new();
+    let mut rest = html;
+    while let Some(at) = rest.find(MARKER) {
+        let chunk = &rest[at..];
+        let Some(value) = script_json(chunk, MARKER) else {
+            rest = &chunk[MARKER.len()..];
+            continue;
+        };
+        collect_json_ld(&value, &mut items);
+        rest = &chunk[MARKER.len()..];
+    }
+    items
+}
+
+fn collect_json_ld(value: &Value, out: &mut Vec<LinkItem>) {
+    match value {
+        Value::Array(list) => list.iter().for_each(|v| collect_json_ld(v, out)),
+        Value::Object(obj) => {
+            if let Some(tracks) = obj.get("track") {
+                let tracks = match tracks {
+                    Value::Array(list) => list.iter().collect(),
+                    single => vec![single],
+                };
+                for t in tracks {
+                    // MusicPlaylist の track は ListItem で包まれていることがある
+                    let t = t.get("item").unwrap_or(t);
+                    let Some(title) = str_field(t, &["name"]) else {
+                        continue;
+                    };
+                    out.push(LinkItem {
+                        title,
+                        artist: t
+                            .get("byArtist")
+                            .and_then(|a| match a {
+                                Value::Array(list) => list.first(),
+                                single => Some(single),
+                            })
+                            .and_then(|a| str_field(a, &["name"])),
+                        duration: t
+                            .get("duration")
+                            .and_then(Value::as_str)
+                            .and_then(parse_iso8601_duration),
+                    });
+                }
+            } else if obj.get("@type").and_then(Value::as_str) == Some("MusicRecording") {
+                if let Some(title) = str_field(value, &["name"]) {
+                    out.push(LinkItem {
+                        title,
+                        artist: value
+                            .pointer("/byArtist/name")
+                            .and_then(Value::as_str)
+                            .map(str::to_string),
+                        duration: value
+                            .get("duration")
+                            .and_then(Value::as_str)
+                            .and_then(parse_iso8601_duration),
+                    });
+                }
+            } else if let Some(graph) = obj.get("@graph") {
+                collect_json_ld(graph, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `PT3M25S` 形式の長さを読む。
+fn parse_iso8601_duration(s: &str) -> Option<Duration> {
+    let rest = s.strip_prefix("PT")?;
+    let mut secs = 0f64;
+    let mut num = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' | '.' => num.push(c),
+            'H' | 'M' | 'S' => {
+                let n: f64 = num.parse().ok()?;
+                num.clear();
+                secs += n * match c {
+                    'H' => 3600.0,
+                    'M' => 60.0,
+                    _ => 1.0,
+                };
+            }
+            _ => return None,
+        }
+    }
+    (secs > 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+fn str_field(v: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|k| v.get(*k).and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
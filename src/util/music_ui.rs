@@ -3,9 +3,13 @@ use poise::serenity_prelude::{ButtonStyle, Colour, CreateActionRow, CreateButton
 use songbird::tracks::PlayMode;
 use url::Url;
 
-use crate::util::track::TrackRequest;
+use crate::util::{
+    queue::{BatchOutcome, QueueError},
+    track::TrackRequest,
+};
 
-fn truncate_chars(s: &str, max_chars: usize) -> String {
+/// `max_chars` 文字を超える分を切り詰め、末尾を `…` にする。
+pub(crate) fn truncate_chars(s: &str, max_chars: usize) -> String {
     if max_chars == 0 {
         return String::new();
     }
@@ -32,11 +36,41 @@ fn truncate_embed_field_value(s: &str) -> String {
 }
 
 /// 秒数を mm:ss 形式に整形する（不明なら "--:--"）。
-fn format_duration(dur: Option<std::time::Duration>) -> String {
+pub(crate) fn format_duration(dur: Option<std::time::Duration>) -> String {
     dur.map(|d| format!("{:02}:{:02}", d.as_secs() / 60, d.as_secs() % 60))
         .unwrap_or_else(|| "--:--".to_string())
 }
 
+/// `mm:ss` / `hh:mm:ss` / 秒数のみ の表記を Duration に変換する。
+pub(crate) fn parse_timestamp(s: &str) -> Option<std::time::Duration> {
+    let parts = s
+        .trim()
+        .split(':')
+        .map(|p| p.trim().parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let secs = match parts.as_slice() {
+        [s] => *s,
+        [m, s] if *s < 60 => m * 60 + s,
+        [h, m, s] if *m < 60 && *s < 60 => h * 3600 + m * 60 + s,
+        _ => return None,
+    };
+    Some(std::time::Duration::from_secs(secs))
+}
+
+/// 再生位置をテキストのプログレスバーで表す。
+pub(crate) fn progress_bar(position: std::time::Duration, length: std::time::Duration) -> String {
+    const WIDTH: usize = 16;
+    let ratio = if length.is_zero() {
+        0.0
+    } else {
+        (position.as_secs_f64() / length.as_secs_f64()).clamp(0.0, 1.0)
+    };
+    let knob = ((WIDTH - 1) as f64 * ratio).round() as usize;
+    (0..WIDTH)
+        .map(|i| if i == knob { '🔘' } else { '▬' })
+        .collect()
+}
+
 /// YouTube の URL からサムネイル URL を導出する。
 fn youtube_thumbnail(url: &str) -> Option<String> {
     let parsed = Url::parse(url).ok()?;
@@ -65,6 +99,17 @@ pub(crate) fn track_embed(
     tr: Option<&TrackRequest>,
     note: Option<String>,
     colour: Colour,
+) -> CreateEmbed {
+    track_embed_at(title, tr, note, colour, None)
+}
+
+/// `track_embed` に加えて、再生位置とプログレスバーを表示する。
+pub(crate) fn track_embed_at(
+    title: &str,
+    tr: Option<&TrackRequest>,
+    note: Option<String>,
+    colour: Colour,
+    position: Option<std::time::Duration>,
 ) -> CreateEmbed {
     let mut embed = CreateEmbed::default()
         .title(truncate_embed_title(title))
@@ -85,6 +130,18 @@ pub(crate) fn track_embed(
             truncate_embed_field_value(&format_duration(tr.meta.duration)),
             true,
         );
+        if let Some(position) = position {
+            let value = match tr.meta.duration {
+                Some(length) => format!(
+                    "`{} / {}`\n{}",
+                    format_duration(Some(position)),
+                    format_duration(Some(length)),
+                    progress_bar(position, length)
+                ),
+                None => format!("`{}` (ライブ)", format_duration(Some(position))),
+            };
+            embed = embed.field("Position", truncate_embed_field_value(&value), true);
+        }
         embed = embed.field(
             "Requested by",
             truncate_embed_field_value(&format!("<@{}>", tr.requested_by)),
@@ -103,24 +160,56 @@ pub(crate) fn track_embed(
     embed
 }
 
+/// キューへの追加を断ったときの表示（`/play`・`/queue`・`/insert` で共通）。
+pub(crate) fn queue_error_embed(req: Option<&TrackRequest>, err: &QueueError) -> CreateEmbed {
+    track_embed(
+        "🚫 キューに追加できませんでした",
+        req,
+        Some(err.to_string()),
+        Colour::new(0xE74C3C),
+    )
+}
+
+/// まとめて追加したときに飛ばした曲があれば、その件数と理由を返す。
+pub(crate) fn batch_skip_note(outcome: &BatchOutcome) -> Option<String> {
+    let reason = outcome.reason.as_ref()?;
+    Some(format!(
+        "{} 件は追加しませんでした（{reason}）",
+        outcome.skipped
+    ))
+}
+
 /// 再生ステートに合わせてボタン行を生成する。
 pub(crate) fn control_components(state: PlayMode) -> Vec<CreateActionRow> {
     let is_playing = matches!(state, PlayMode::Play);
     let is_paused = matches!(state, PlayMode::Pause);
-    vec![CreateActionRow::Buttons(vec![
-        CreateButton::new("music_pause")
-            .label("⏸ 一時停止")
-            .style(ButtonStyle::Secondary)
-            .disabled(!is_playing),
-        CreateButton::new("music_resume")
-            .label("▶ 再開")
-            .style(ButtonStyle::Secondary)
-            .disabled(!is_paused),
-        CreateButton::new("music_skip")
-            .label("⏭ 次の曲へ")
-            .style(ButtonStyle::Primary),
-        CreateButton::new("music_stop")
-            .label("⏹ 停止")
-            .style(ButtonStyle::Danger),
-    ])]
+    let has_track = is_playing || is_paused;
+    vec![
+        CreateActionRow::Buttons(vec![
+            CreateButton::new("music_pause")
+                .label("⏸ 一時停止")
+                .style(ButtonStyle::Secondary)
+                .disabled(!is_playing),
+            CreateButton::new("music_resume")
+                .label("▶ 再開")
+                .style(ButtonStyle::Secondary)
+                .disabled(!is_paused),
+            CreateButton::new("music_skip")
+                .label("⏭ 次の曲へ")
+                .style(ButtonStyle::Primary),
+            CreateButton::new("music_stop")
+                .label("⏹ 停止")
+                .style(ButtonStyle::Danger),
+        ]),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new("music_rewind")
+                .label("⏪ 10秒")
+                .style(ButtonStyle::Secondary)
+                .disabled(!has_track),
+            CreateButton::new("music_forward")
+                .label("⏩ 10秒")
+                .style(ButtonStyle::Secondary)
+                .disabled(!has_track),
+        ]),
+    ]
 }
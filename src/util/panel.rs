@@ -0,0 +1,195 @@
+use std::{sync::Arc, time::Duration};
+
+use lavalink_rs::client::LavalinkClient;
+use poise::serenity_prelude::{self as serenity, Colour, EditMessage, GuildId, HttpError};
+use songbird::tracks::PlayMode;
+
+use crate::{
+    GLOBAL_CONFIG,
+    util::{
+        lavalink_player::{LavalinkRuntimeData, current_play_mode},
+        music_ui::{control_components, track_embed_at},
+        types::PanelUpdaters,
+        vote::tally,
+    },
+};
+
+const DEFAULT_INTERVAL_SECS: u64 = 15;
+const MIN_INTERVAL_SECS: u64 = 5;
+/// 編集に失敗し続けた場合の待ち時間の上限。
+const MAX_BACKOFF: Duration = Duration::from_secs(120);
+
+fn update_interval() -> Duration {
+    let secs = GLOBAL_CONFIG
+        .music
+        .as_ref()
+        .and_then(|m| m.panel_update_secs)
+        .unwrap_or(DEFAULT_INTERVAL_SECS)
+        .max(MIN_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+/// タスク終了時に登録を外す。
+struct Registration {
+    updaters: PanelUpdaters,
+    guild_id: GuildId,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.updaters.remove(&self.guild_id);
+    }
+}
+
+fn status_code(err: &serenity::Error) -> Option<u16> {
+    match err {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(resp)) => {
+            Some(resp.status_code.as_u16())
+        }
+        _ => None,
+    }
+}
+
+/// 再生パネルを定期的に更新するタスクを（未起動なら）起動する。
+/// パネルが削除されるか再生が止まった時点で終了する。
+pub fn ensure_updater(lavalink: Arc<LavalinkClient>, guild_id: GuildId) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    if !runtime.panel_updaters.insert(guild_id) {
+        return;
+    }
+    let registration = Registration {
+        updaters: runtime.panel_updaters.clone(),
+        guild_id,
+    };
+
+    tokio::spawn(async move {
+        let _registration = registration;
+        let interval = update_interval();
+        let mut wait = interval;
+        let mut last_rendered = None;
+
+        loop {
+            tokio::time::sleep(wait).await;
+
+            let Some((channel_id, message_id)) =
+                runtime.now_playing.get(&guild_id).map(|e| *e.value())
+            else {
+                return;
+            };
+            let Some(req) = runtime
+                .lavalink_playing
+                .get(&guild_id)
+                .map(|e| e.value().clone())
+            else {
+                return;
+            };
+            let Some(player) = lavalink.get_player_context(guild_id) else {
+                return;
+            };
+            let Ok(state) = player.get_player().await else {
+                wait = interval;
+                continue;
+            };
+            if state.track.is_none() {
+                return;
+            }
+
+            let position = Duration::from_millis(state.state.position);
+            let (next, remaining) = match runtime.queues.get_mut(&guild_id) {
+                Some(mut q) => {
+                    let next = q
+                        .peek_next()
+                        .map(|n| n.meta.title.clone().unwrap_or_else(|| n.url.clone()));
+                    (next, q.len())
+                }
+                None => (None, 0),
+            };
+
+            let settings = runtime.settings.get(guild_id);
+            let votes = if settings.vote_skip {
+                tally(
+                    &runtime.cache,
+                    &runtime.voice_channels,
+                    &runtime.lavalink_playing,
+                    &runtime.votes,
+                    guild_id,
+                    settings.vote_skip_ratio,
+                )
+                .filter(|(count, _)| *count > 0)
+            } else {
+                None
+            };
+
+            // 一時停止中など表示が変わらない場合は編集しない
+            let rendered = (
+                req.url.clone(),
+                position.as_secs(),
+                state.paused,
+                next.clone(),
+                votes,
+            );
+            if last_rendered.as_ref() == Some(&rendered) {
+                wait = interval;
+                continue;
+            }
+
+            let mut note = match next {
+                Some(next) => format!("次の曲: {next}\nキュー残り {remaining} 件"),
+                None => "次の曲: なし".to_string(),
+            };
+            if let Some((count, needed)) = votes {
+                note.push_str(&format!("\n🗳️ スキップ投票 {count}/{needed}"));
+            }
+            let (title, colour) = if state.paused {
+                ("⏸ 一時停止中", Colour::new(0x5865F2))
+            } else {
+                ("🎵 再生中", Colour::new(0x2ECC71))
+            };
+            let embed = track_embed_at(title, Some(&req), Some(note), colour, Some(position));
+            let mode = current_play_mode(&lavalink, guild_id).await;
+            let components = control_components(if matches!(mode, PlayMode::Stop) {
+                PlayMode::Play
+            } else {
+                mode
+            });
+
+            match channel_id
+                .edit_message(
+                    &runtime.http,
+                    message_id,
+                    EditMessage::new()
+                        .embeds(vec![embed])
+                        .components(components),
+                )
+                .await
+            {
+                Ok(_) => {
+                    last_rendered = Some(rendered);
+                    wait = interval;
+                }
+                Err(err) => match status_code(&err) {
+                    Some(404) | Some(403) => {
+                        // パネルが削除された（または見えなくなった）ので登録ごと破棄する
+                        runtime
+                            .now_playing
+                            .remove_if(&guild_id, |_, v| v.1 == message_id);
+                        tracing::debug!(guild = %guild_id, "now-playing panel is gone; stopping updater");
+                        return;
+                    }
+                    code => {
+                        wait = (wait * 2).min(MAX_BACKOFF);
+                        tracing::debug!(
+                            guild = %guild_id,
+                            status = ?code,
+                            error = %err,
+                            backoff_secs = wait.as_secs(),
+                            "failed to update now-playing panel"
+                        );
+                    }
+                },
+            }
+        }
+    });
+}
@@ -0,0 +1,125 @@
+use poise::serenity_prelude::{Cache, ComponentInteraction, GuildId, Member, RoleId, UserId};
+
+use crate::util::{
+    alias::{Context, Error},
+    settings::GuildSettings,
+};
+
+/// 権限が必要な音楽操作。
+#[derive(Clone, Copy, Debug)]
+pub enum MusicAction {
+    /// 一時停止・再開・シーク・音量などの再生操作
+    Control,
+    /// 現在の曲のスキップ（`requester` は再生中の曲のリクエスト者）
+    Skip { requester: Option<UserId> },
+    /// 停止とキューのクリア
+    Stop,
+    /// キューからの削除（`requester` は対象曲のリクエスト者）
+    Remove { requester: Option<UserId> },
+    /// 先頭への割り込み追加
+    Insert,
+}
+
+/// 操作しようとしているメンバー。
+pub struct Actor {
+    pub user_id: UserId,
+    roles: Vec<RoleId>,
+    is_admin: bool,
+}
+
+impl Actor {
+    pub fn from_member(cache: &Cache, guild_id: GuildId, member: &Member) -> Self {
+        // インタラクション由来のメンバーは権限が解決済み。プレフィックスコマンドはキャッシュから計算する。
+        let permissions = member
+            .permissions
+            .or_else(|| cache.guild(guild_id).map(|g| g.member_permissions(member)));
+        let is_owner = cache
+            .guild(guild_id)
+            .is_some_and(|g| g.owner_id == member.user.id);
+        Self {
+            user_id: member.user.id,
+            roles: member.roles.clone(),
+            is_admin: is_owner
+                || permissions.is_some_and(|p| p.administrator() || p.manage_guild()),
+        }
+    }
+
+    /// 管理者、または DJ ロール保持者（DJ ロール未設定時は全員）。
+    pub fn is_dj(&self, settings: &GuildSettings) -> bool {
+        self.is_admin
+            || match settings.dj_role() {
+                Some(role) => self.roles.contains(&role),
+                None => true,
+            }
+    }
+}
+
+/// 操作を許可するか判定し、拒否時は理由を返す。
+pub fn check(settings: &GuildSettings, actor: &Actor, action: MusicAction) -> Result<(), String> {
+    if actor.is_dj(settings) {
+        return Ok(());
+    }
+    let own = |requester: Option<UserId>| {
+        settings.requester_can_manage && requester == Some(actor.user_id)
+    };
+    let allowed = match action {
+        MusicAction::Skip { requester } | MusicAction::Remove { requester } => own(requester),
+        MusicAction::Control | MusicAction::Stop | MusicAction::Insert => false,
+    };
+    if allowed {
+        return Ok(());
+    }
+
+    let role = settings
+        .dj_role()
+        .map(|r| format!("<@&{r}>"))
+        .unwrap_or_else(|| "DJ".to_string());
+    Err(match action {
+        MusicAction::Skip { .. } | MusicAction::Remove { .. } if settings.requester_can_manage => {
+            format!("🚫 この操作は {role} ロールか、曲をリクエストした本人のみ可能です")
+        }
+        _ => format!("🚫 この操作は {role} ロールを持つメンバーのみ可能です"),
+    })
+}
+
+/// コマンド実行者を Actor として取得する。
+pub async fn command_actor(ctx: &Context<'_>) -> Result<Actor, Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let Some(member) = ctx.author_member().await else {
+        return Err("メンバー情報を取得できませんでした".into());
+    };
+    Ok(Actor::from_member(ctx.cache(), guild_id, &member))
+}
+
+/// コマンド実行者の権限を確認し、拒否した場合はその旨を返信して false を返す。
+pub async fn require(ctx: &Context<'_>, action: MusicAction) -> Result<bool, Error> {
+    let guild_id = ctx.guild_id().ok_or("サーバー内で実行してください")?;
+    let actor = command_actor(ctx).await?;
+    let settings = ctx.data().settings.get(guild_id);
+    match check(&settings, &actor, action) {
+        Ok(()) => Ok(true),
+        Err(reason) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(reason)
+                    .ephemeral(true),
+            )
+            .await?;
+            Ok(false)
+        }
+    }
+}
+
+/// ボタン操作の押下者の権限を確認する。
+pub fn check_interaction(
+    ctx: &Context<'_>,
+    guild_id: GuildId,
+    interaction: &ComponentInteraction,
+    action: MusicAction,
+) -> Result<(), String> {
+    let Some(member) = interaction.member.as_ref() else {
+        return Err("メンバー情報を取得できませんでした".to_string());
+    };
+    let actor = Actor::from_member(ctx.cache(), guild_id, member);
+    check(&ctx.data().settings.get(guild_id), &actor, action)
+}
@@ -1,6 +1,9 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 
 use poise::serenity_prelude::GuildId;
@@ -14,6 +17,22 @@ pub enum PlaybackControlResult {
     Missing,
 }
 
+/// シーク先の指定方法。
+#[derive(Clone, Copy, Debug)]
+pub enum SeekTarget {
+    Absolute(Duration),
+    Forward(Duration),
+    Backward(Duration),
+}
+
+#[derive(Clone, Debug)]
+pub enum SeekResult {
+    /// 移動後の位置と曲の長さ
+    Moved(TrackRequest, Duration, Duration),
+    NotSeekable,
+    Missing,
+}
+
 pub struct ManualTransitionGuard {
     flag: Arc<AtomicBool>,
 }
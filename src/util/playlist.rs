@@ -1,11 +1,218 @@
 use crate::util::{
     alias::Error,
+    lavalink_player::apply_track_metadata,
+    music_links::{self, fetch_text},
+    track::{TrackRequest, youtube_video_id},
     ytdlp::{cookies_args, extra_args_from_config},
 };
+use futures::future::BoxFuture;
+use lavalink_rs::{
+    client::LavalinkClient,
+    model::track::{TrackData, TrackLoadData},
+};
+use poise::serenity_prelude::{GuildId, UserId};
+use rand::seq::SliceRandom;
 use serde_json::Value;
-use std::time::Duration;
+use std::{collections::BTreeMap, fmt, str::FromStr, time::Duration};
 use url::Url;
 
+/// プレイリストを展開するときに使う情報。
+pub struct PlaylistContext<'a> {
+    pub lavalink: Option<&'a LavalinkClient>,
+    pub guild_id: GuildId,
+    pub requested_by: UserId,
+}
+
+/// 複数の曲に展開できる入力（プレイリスト・アルバムなど）の取得元。
+///
+/// `expand` が 1 件だけ返した場合は単曲として扱い、空なら展開できなかったものとして
+/// 通常の 1 曲の解決に回す。
+pub trait PlaylistProvider: Sync {
+    /// メッセージ表示用の名前
+    fn name(&self) -> &'static str;
+    fn matches(&self, raw: &str) -> bool;
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>>;
+}
+
+/// 判定順に並べた取得元。Lavalink は他に当てはまらない URL の受け皿なので最後に置く。
+static PROVIDERS: &[&dyn PlaylistProvider] = &[
+    &YouTubePlaylist,
+    &SoundCloudSet,
+    &BandcampAlbum,
+    &RemotePlaylistFile,
+    &MusicLinks,
+    &LavalinkPlaylist,
+];
+
+/// 入力を扱える取得元を探す。
+pub fn find_provider(raw: &str) -> Option<&'static dyn PlaylistProvider> {
+    let raw = raw.trim();
+    PROVIDERS.iter().copied().find(|s| s.matches(raw))
+}
+
+/// プレイリストから取り出す範囲（1 始まり・両端を含む）。
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct InvalidRange;
+
+impl fmt::Display for InvalidRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("範囲は `20-60` / `20-` / `-60` の形で指定してください")
+    }
+}
+
+impl std::error::Error for InvalidRange {}
+
+/// `20-60`、`20-`（20 曲目以降）、`-60`（60 曲目まで）を受け付ける。
+/// プレフィックスコマンドで検索語の先頭を範囲と誤認しないよう `-` を必須にしている。
+impl FromStr for PlaylistRange {
+    type Err = InvalidRange;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s.trim().split_once('-').ok_or(InvalidRange)?;
+        let start = match from.trim() {
+            "" => 1,
+            n => n.parse().map_err(|_| InvalidRange)?,
+        };
+        let end = match to.trim() {
+            "" => None,
+            n => Some(n.parse().map_err(|_| InvalidRange)?),
+        };
+        if start == 0 || end.is_some_and(|e| e < start) {
+            return Err(InvalidRange);
+        }
+        Ok(Self { start, end })
+    }
+}
+
+/// `/play` `/queue` でプレイリストを展開するときの指定。
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PlaylistOptions {
+    pub range: Option<PlaylistRange>,
+    /// 展開した曲をシャッフルしてからキューに入れる
+    pub shuffle: bool,
+}
+
+/// 範囲・シャッフルを反映した展開結果。
+pub struct Expanded {
+    pub requests: Vec<TrackRequest>,
+    /// URL の `t` で指定された、1 曲目の再生開始位置
+    pub start_at: Option<Duration>,
+}
+
+/// 取得元で展開し、範囲指定（無ければ URL の `index`）とシャッフルを反映する。
+/// 取り出す曲数は `cap`（ギルド設定のプレイリスト上限）までに抑える。
+pub async fn expand_with_options(
+    provider: &dyn PlaylistProvider,
+    cx: &PlaylistContext<'_>,
+    raw: &str,
+    options: PlaylistOptions,
+    cap: usize,
+) -> Result<Expanded, Error> {
+    let cap = cap.max(1);
+    let explicit = options.range.is_some();
+    let range = options
+        .range
+        .or_else(|| url_index(raw).map(|start| PlaylistRange { start, end: None }));
+    let (skip, take) = match range {
+        Some(r) => (
+            r.start - 1,
+            r.end.map_or(cap, |end| end + 1 - r.start).min(cap),
+        ),
+        None => (0, cap),
+    };
+
+    let mut requests = provider.expand(cx, raw, skip + take).await?;
+    // 単曲（1 件）や展開できなかった URL は、範囲を明示したときだけ切り出す
+    if !requests.is_empty() && (requests.len() > 1 || explicit) {
+        if skip >= requests.len() {
+            return Err(Error::from(format!(
+                "プレイリストに {} 曲目がありません（{} 曲）",
+                skip + 1,
+                requests.len()
+            )));
+        }
+        requests = requests.into_iter().skip(skip).take(take).collect();
+    }
+    if options.shuffle && requests.len() > 1 {
+        requests.shuffle(&mut rand::rng());
+    }
+
+    // `t` は URL の `v` の動画に対する指定なので、その動画が先頭に来たときだけ使う
+    let start_at = url_start_time(raw).filter(|_| {
+        let first = requests.first().and_then(|r| youtube_video_id(&r.url));
+        first.is_some() && first == youtube_video_id(raw)
+    });
+    Ok(Expanded { requests, start_at })
+}
+
+/// YouTube プレイリスト URL の `index`（1 始まり）。
+fn url_index(raw: &str) -> Option<usize> {
+    if !is_youtube_playlist_url(raw) {
+        return None;
+    }
+    Url::parse(raw)
+        .ok()?
+        .query_pairs()
+        .find_map(|(k, v)| (k == "index").then(|| v.trim().parse::<usize>().ok()))
+        .flatten()
+        .filter(|&i| i >= 1)
+}
+
+/// URL の `t` / `start`（`90`、`90s`、`1m30s`、`1h2m3s`）。
+fn url_start_time(raw: &str) -> Option<Duration> {
+    let url = Url::parse(raw).ok()?;
+    let value = url
+        .query_pairs()
+        .find_map(|(k, v)| matches!(k.as_ref(), "t" | "start").then(|| v.into_owned()))?;
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return (secs > 0).then(|| Duration::from_secs(secs));
+    }
+    let mut secs = 0u64;
+    let mut num = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            num.push(c);
+            continue;
+        }
+        let n: u64 = num.parse().ok()?;
+        num.clear();
+        secs += n * match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+    }
+    if !num.is_empty() {
+        return None;
+    }
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+fn parse_http(raw: &str) -> Option<Url> {
+    Url::parse(raw.trim())
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+}
+
+fn host_of(url: &Url) -> &str {
+    url.host_str()
+        .unwrap_or_default()
+        .trim_start_matches("www.")
+}
+
 pub fn is_youtube_playlist_url(raw: &str) -> bool {
     let Ok(url) = Url::parse(raw) else {
         return false;
@@ -21,9 +228,365 @@ pub fn is_youtube_playlist_url(raw: &str) -> bool {
         .any(|(k, v)| k == "list" && !v.trim().is_empty())
 }
 
-pub async fn expand_youtube_playlist(raw: &str, limit: usize) -> Result<Vec<String>, Error> {
-    let limit = limit.max(1);
+struct YouTubePlaylist;
+
+impl PlaylistProvider for YouTubePlaylist {
+    fn name(&self) -> &'static str {
+        "YouTube プレイリスト"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        is_youtube_playlist_url(raw)
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(async move { expand_youtube_playlist(raw, limit, cx.requested_by).await })
+    }
+}
+
+/// `soundcloud.com/<user>/sets/<name>`
+struct SoundCloudSet;
+
+impl PlaylistProvider for SoundCloudSet {
+    fn name(&self) -> &'static str {
+        "SoundCloud セット"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        parse_http(raw).is_some_and(|u| {
+            host_of(&u).ends_with("soundcloud.com")
+                && u.path_segments()
+                    .is_some_and(|mut s| s.any(|p| p == "sets"))
+        })
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(expand_via_lavalink_or_ytdlp(cx, raw, limit))
+    }
+}
+
+/// `<artist>.bandcamp.com/album/<name>`
+struct BandcampAlbum;
+
+impl PlaylistProvider for BandcampAlbum {
+    fn name(&self) -> &'static str {
+        "Bandcamp アルバム"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        parse_http(raw).is_some_and(|u| {
+            host_of(&u).ends_with("bandcamp.com")
+                && u.path_segments()
+                    .is_some_and(|mut s| s.next() == Some("album"))
+        })
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(expand_via_lavalink_or_ytdlp(cx, raw, limit))
+    }
+}
+
+/// Lavalink で読めればそれを使い、だめなら yt-dlp で曲 URL を列挙する。
+async fn expand_via_lavalink_or_ytdlp(
+    cx: &PlaylistContext<'_>,
+    raw: &str,
+    limit: usize,
+) -> Result<Vec<TrackRequest>, Error> {
+    if let Some(lavalink) = cx.lavalink {
+        match load_with_lavalink(lavalink, cx.guild_id, raw, limit, cx.requested_by).await {
+            Ok(reqs) if !reqs.is_empty() => return Ok(reqs),
+            Ok(_) => tracing::info!(url = %raw, "lavalink returned no tracks; trying yt-dlp"),
+            Err(err) => {
+                tracing::warn!(url = %raw, error = %err, "lavalink playlist load failed; trying yt-dlp")
+            }
+        }
+    }
+    expand_with_ytdlp(raw, limit, cx.requested_by).await
+}
 
+/// `.m3u` / `.m3u8` / `.pls` のプレイリストファイル。
+struct RemotePlaylistFile;
+
+impl PlaylistProvider for RemotePlaylistFile {
+    fn name(&self) -> &'static str {
+        "プレイリストファイル"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        parse_http(raw).is_some_and(|u| {
+            let path = u.path().to_ascii_lowercase();
+            path.ends_with(".m3u") || path.ends_with(".m3u8") || path.ends_with(".pls")
+        })
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(async move {
+            let base = Url::parse(raw.trim())?;
+            let text = fetch_text(base.as_str()).await?;
+            // HLS（ラジオ配信など）はそれ自体が 1 本のストリーム
+            if text.contains("#EXT-X-") {
+                return Ok(vec![TrackRequest::new(
+                    raw.trim().to_string(),
+                    cx.requested_by,
+                )]);
+            }
+            let entries = if base.path().to_ascii_lowercase().ends_with(".pls") {
+                parse_pls(&text, &base)
+            } else {
+                parse_m3u(&text, Some(&base))
+            };
+            if entries.is_empty() {
+                return Err(Error::from(
+                    "プレイリストファイルに再生できる URL がありませんでした",
+                ));
+            }
+            Ok(entries
+                .into_iter()
+                .take(limit.max(1))
+                .map(|(url, title, duration)| {
+                    let mut req = TrackRequest::new(url, cx.requested_by);
+                    req.meta.title = title;
+                    req.meta.duration = duration;
+                    req
+                })
+                .collect())
+        })
+    }
+}
+
+/// プレイリストファイルの 1 項目（URL・曲名・長さ）。
+pub(crate) type FileEntry = (String, Option<String>, Option<Duration>);
+
+/// 相対パスはプレイリストファイルの場所（`base`）から解決し、http(s) 以外は捨てる。
+fn entry_url(line: &str, base: Option<&Url>) -> Option<String> {
+    let line = line.trim();
+    match base {
+        Some(base) => base.join(line),
+        None => Url::parse(line),
+    }
+    .ok()
+    .filter(|u| matches!(u.scheme(), "http" | "https"))
+    .map(String::from)
+}
+
+/// M3U を読む（`#EXTINF:<秒>,<タイトル>` があれば曲名・長さも取る）。
+/// 保存済みプレイリストの取り込みでも使う。その場合は `base` が無いので絶対 URL だけを拾う。
+pub(crate) fn parse_m3u(text: &str, base: Option<&Url>) -> Vec<FileEntry> {
+    let mut out = Vec::new();
+    let mut pending: Option<(Option<Duration>, Option<String>)> = None;
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (secs, title) = info.split_once(',').unwrap_or((info, ""));
+            let duration = secs
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .filter(|&s| s > 0)
+                .map(|s| Duration::from_secs(s as u64));
+            let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+            pending = Some((duration, title));
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        // 再生できない行（ローカルファイルのパスなど）でも、直前の #EXTINF はここで捨てる
+        let (duration, title) = pending.take().unwrap_or_default();
+        if let Some(url) = entry_url(line, base) {
+            out.push((url, title, duration));
+        }
+    }
+    out
+}
+
+fn parse_pls(text: &str, base: &Url) -> Vec<FileEntry> {
+    // File1= / Title1= / Length1= を番号ごとにまとめる
+    let mut entries: BTreeMap<u32, (Option<String>, Option<String>, Option<Duration>)> =
+        BTreeMap::new();
+    for line in text.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let field = ["file", "title", "length"]
+            .into_iter()
+            .find_map(|f| Some((f, key.strip_prefix(f)?.parse::<u32>().ok()?)));
+        let Some((field, n)) = field else {
+            continue;
+        };
+        let entry = entries.entry(n).or_default();
+        match field {
+            "file" => entry.0 = entry_url(value, Some(base)),
+            "title" => entry.1 = Some(value.to_string()).filter(|t| !t.is_empty()),
+            _ => {
+                entry.2 = value
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|&s| s > 0)
+                    .map(|s| Duration::from_secs(s as u64))
+            }
+        }
+    }
+    entries
+        .into_values()
+        .filter_map(|(url, title, duration)| Some((url?, title, duration)))
+        .collect()
+}
+
+/// Spotify / Apple Music / Deezer のリンク（`music_links` で解決する）。
+struct MusicLinks;
+
+impl PlaylistProvider for MusicLinks {
+    fn name(&self) -> &'static str {
+        "Spotify / Apple Music / Deezer"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        music_links::detect(raw).is_some()
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(async move {
+            let link =
+                music_links::detect(raw).ok_or_else(|| Error::from("対応していないリンクです"))?;
+            music_links::resolve(cx.lavalink, cx.guild_id, &link, limit, cx.requested_by).await
+        })
+    }
+}
+
+/// 他に当てはまらないプレイリストらしい URL を Lavalink に読ませ、プレイリストが返れば全曲を使う。
+struct LavalinkPlaylist;
+
+/// URL のパスにこれらの区切りがあればプレイリスト・アルバムとみなす。
+const PLAYLIST_PATH_SEGMENTS: &[&str] =
+    &["playlist", "playlists", "album", "albums", "sets", "mix"];
+
+impl PlaylistProvider for LavalinkPlaylist {
+    fn name(&self) -> &'static str {
+        "Lavalink"
+    }
+
+    fn matches(&self, raw: &str) -> bool {
+        // 単曲の URL まで読ませると、再生時の解決と合わせて 2 回読み込むことになるので、
+        // プレイリストらしいものだけを対象にする（YouTube は専用の取得元で扱う）
+        parse_http(raw).is_some_and(|u| {
+            let host = host_of(&u);
+            if host.contains("youtube.com") || host.contains("youtu.be") {
+                return false;
+            }
+            let by_path = u.path_segments().is_some_and(|mut segs| {
+                segs.any(|seg| PLAYLIST_PATH_SEGMENTS.contains(&seg.to_ascii_lowercase().as_str()))
+            });
+            by_path || u.query_pairs().any(|(k, _)| k == "list" || k == "playlist")
+        })
+    }
+
+    fn expand<'a>(
+        &'a self,
+        cx: &'a PlaylistContext<'a>,
+        raw: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<TrackRequest>, Error>> {
+        Box::pin(async move {
+            let Some(lavalink) = cx.lavalink else {
+                return Ok(Vec::new());
+            };
+            // 読めない URL は通常の解決に任せる
+            match load_with_lavalink(lavalink, cx.guild_id, raw, limit, cx.requested_by).await {
+                Ok(reqs) => Ok(reqs),
+                Err(err) => {
+                    tracing::debug!(url = %raw, error = %err, "lavalink could not load url as playlist");
+                    Ok(Vec::new())
+                }
+            }
+        })
+    }
+}
+
+/// Lavalink で読み込み、プレイリストなら最大 `limit` 曲、単曲なら 1 曲を返す。
+pub(crate) async fn load_with_lavalink(
+    lavalink: &LavalinkClient,
+    guild_id: GuildId,
+    identifier: &str,
+    limit: usize,
+    requested_by: UserId,
+) -> Result<Vec<TrackRequest>, Error> {
+    let load = lavalink
+        .load_tracks(guild_id, identifier)
+        .await
+        .map_err(|e| Error::from(format!("Lavalink load_tracks error: {e}")))?;
+    let single = matches!(load.data, Some(TrackLoadData::Track(_)));
+    let tracks = match load.data {
+        Some(TrackLoadData::Track(track)) => vec![track],
+        Some(TrackLoadData::Playlist(playlist)) => playlist.tracks,
+        Some(TrackLoadData::Search(tracks)) => tracks.into_iter().take(1).collect(),
+        Some(TrackLoadData::Error(err)) => {
+            return Err(Error::from(format!(
+                "Lavalink track load failed: {}",
+                err.message
+            )));
+        }
+        None => Vec::new(),
+    };
+    let mut unplayable = 0usize;
+    let reqs: Vec<TrackRequest> = tracks
+        .iter()
+        .filter_map(|track| {
+            // 単曲なら入力そのものが曲を指すが、プレイリストの URL を各曲に使うと
+            // 再生のたびにプレイリスト全体を読み直して 1 曲目を流すことになる
+            let url = lavalink_track_url(track).or_else(|| single.then(|| identifier.to_string()));
+            if url.is_none() {
+                unplayable += 1;
+            }
+            let mut req = TrackRequest::new(url?, requested_by);
+            apply_track_metadata(&mut req, track);
+            Some(req)
+        })
+        .take(limit.max(1))
+        .collect();
+    if unplayable > 0 {
+        tracing::debug!(url = %identifier, unplayable, "skipped lavalink tracks without a uri");
+    }
+    Ok(reqs)
+}
+
+/// Lavalink の曲を単独で読み直せる URL。URI が無ければ YouTube の動画 ID から組み立てる。
+fn lavalink_track_url(track: &TrackData) -> Option<String> {
+    if let Some(uri) = track.info.uri.as_deref().filter(|u| !u.trim().is_empty()) {
+        return Some(uri.to_string());
+    }
+    (track.info.source_name == "youtube")
+        .then(|| format!("https://www.youtube.com/watch?v={}", track.info.identifier))
+}
+
+/// yt-dlp の `--flat-playlist` でプレイリストの各項目を取り出す。
+async fn ytdlp_playlist_entries(raw: &str) -> Result<Vec<Value>, Error> {
     let mut cmd = tokio::process::Command::new("yt-dlp");
     cmd.arg("--ignore-config")
         .arg("--no-warnings")
@@ -47,41 +610,66 @@ pub async fn expand_youtube_playlist(raw: &str, limit: usize) -> Result<Vec<Stri
         )));
     }
 
-    let json: Value = serde_json::from_slice(&output.stdout)
+    let mut json: Value = serde_json::from_slice(&output.stdout)
         .map_err(|e| Error::from(format!("yt-dlp (playlist) JSON パース失敗: {e}")))?;
 
-    let mut out = Vec::new();
-    let entries = json
-        .get("entries")
-        .and_then(|v| v.as_array())
-        .ok_or_else(|| Error::from("yt-dlp (playlist) の entries が見つかりませんでした"))?;
+    match json.get_mut("entries").map(Value::take) {
+        Some(Value::Array(entries)) => Ok(entries),
+        _ => Err(Error::from(
+            "yt-dlp (playlist) の entries が見つかりませんでした",
+        )),
+    }
+}
 
+fn absolute_entry_url(entry: &Value) -> Option<String> {
+    ["webpage_url", "url"].iter().find_map(|k| {
+        entry
+            .get(*k)
+            .and_then(|v| v.as_str())
+            .filter(|s| s.starts_with("http://") || s.starts_with("https://"))
+            .map(str::to_string)
+    })
+}
+
+/// `--flat-playlist` の項目に含まれる曲名・長さを付けておく（長さの制限を確認できるように）。
+fn entry_request(entry: &Value, url: String, requested_by: UserId) -> TrackRequest {
+    let mut req = TrackRequest::new(url, requested_by);
+    req.meta.title = entry
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|t| !t.trim().is_empty())
+        .map(str::to_string);
+    req.meta.duration = entry
+        .get("duration")
+        .and_then(Value::as_f64)
+        .filter(|secs| *secs > 0.0)
+        .map(Duration::from_secs_f64);
+    req
+}
+
+pub async fn expand_youtube_playlist(
+    raw: &str,
+    limit: usize,
+    requested_by: UserId,
+) -> Result<Vec<TrackRequest>, Error> {
+    let limit = limit.max(1);
+    let entries = ytdlp_playlist_entries(raw).await?;
+
+    let mut out = Vec::new();
     for entry in entries {
         if out.len() >= limit {
             break;
         }
-        let url = entry
-            .get("webpage_url")
-            .and_then(|v| v.as_str())
-            .map(str::to_string)
-            .or_else(|| {
-                entry.get("url").and_then(|v| v.as_str()).map(|s| {
-                    if s.starts_with("http://") || s.starts_with("https://") {
-                        s.to_string()
-                    } else {
-                        format!("https://www.youtube.com/watch?v={s}")
-                    }
-                })
-            })
-            .or_else(|| {
-                entry
-                    .get("id")
-                    .and_then(|v| v.as_str())
-                    .map(|id| format!("https://www.youtube.com/watch?v={id}"))
-            });
+        let url = absolute_entry_url(&entry).or_else(|| {
+            entry
+                .get("url")
+                .or_else(|| entry.get("id"))
+                .and_then(|v| v.as_str())
+                .map(|id| format!("https://www.youtube.com/watch?v={id}"))
+        });
 
         if let Some(u) = url {
-            out.push(u);
+            out.push(entry_request(&entry, u, requested_by));
         }
     }
 
@@ -90,3 +678,23 @@ pub async fn expand_youtube_playlist(raw: &str, limit: usize) -> Result<Vec<Stri
     }
     Ok(out)
 }
+
+/// YouTube 以外のサイトのプレイリストを yt-dlp で展開する（各曲のページ URL を使う）。
+pub async fn expand_with_ytdlp(
+    raw: &str,
+    limit: usize,
+    requested_by: UserId,
+) -> Result<Vec<TrackRequest>, Error> {
+    let out: Vec<TrackRequest> = ytdlp_playlist_entries(raw)
+        .await?
+        .iter()
+        .filter_map(|entry| {
+            absolute_entry_url(entry).map(|u| entry_request(entry, u, requested_by))
+        })
+        .take(limit.max(1))
+        .collect();
+    if out.is_empty() {
+        return Err(Error::from("プレイリストから曲を取得できませんでした"));
+    }
+    Ok(out)
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use lavalink_rs::{client::LavalinkClient, model::track::TrackData};
+use poise::serenity_prelude::GuildId;
+
+use crate::util::{
+    lavalink_player::{LavalinkRuntimeData, resolve_track},
+    repeat::RepeatMode,
+};
+
+/// 残り時間がこれを下回ったら、まだ先読みしていない次曲を解決し直す。
+const LATE_PREFETCH_WINDOW_MS: u64 = 30_000;
+
+/// 次に再生される曲の URL（リピート 1 曲時は再生中の曲）。
+fn next_url(runtime: &LavalinkRuntimeData, guild_id: GuildId) -> Option<String> {
+    let default_repeat = runtime.settings.get(guild_id).default_repeat;
+    let mut q = runtime.queues.get_mut(&guild_id)?;
+    if matches!(q.config.repeat_mode_or(default_repeat), RepeatMode::Track) {
+        drop(q);
+        return runtime
+            .lavalink_playing
+            .get(&guild_id)
+            .map(|e| e.value().url.clone());
+    }
+    q.peek_next().map(|req| req.url.clone())
+}
+
+/// 次曲を TrackData まで解決しておき、曲の切り替え時に load_tracks を待たずに済むようにする。
+pub fn spawn_prefetch(lavalink: Arc<LavalinkClient>, guild_id: GuildId) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let Some(url) = next_url(&runtime, guild_id) else {
+        runtime.prefetched.remove(&guild_id);
+        return;
+    };
+    if runtime
+        .prefetched
+        .get(&guild_id)
+        .is_some_and(|e| e.value().0 == url)
+    {
+        return;
+    }
+
+    tokio::spawn(async move {
+        match resolve_track(&lavalink, guild_id, &url).await {
+            Ok(track) => {
+                // 解決中にキューが変わっていたら捨てる
+                if next_url(&runtime, guild_id).as_deref() == Some(url.as_str()) {
+                    tracing::debug!(guild = %guild_id, title = %track.info.title, "prefetched next track");
+                    runtime.prefetched.insert(guild_id, (url, track));
+                }
+            }
+            Err(err) => {
+                tracing::debug!(guild = %guild_id, url = %url, error = %err, "failed to prefetch next track");
+            }
+        }
+    });
+}
+
+/// 再生終盤に入っても次曲が未解決なら先読みをやり直す（後から追加された曲向け）。
+pub fn prefetch_if_near_end(lavalink: Arc<LavalinkClient>, guild_id: GuildId, position_ms: u64) {
+    let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() else {
+        return;
+    };
+    let Some(length) = runtime
+        .lavalink_playing
+        .get(&guild_id)
+        .and_then(|e| e.value().meta.duration)
+    else {
+        return;
+    };
+    let remaining = (length.as_millis() as u64).saturating_sub(position_ms);
+    if remaining <= LATE_PREFETCH_WINDOW_MS {
+        spawn_prefetch(lavalink, guild_id);
+    }
+}
+
+/// `url` に対応する先読み済みトラックがあれば取り出す。
+pub fn take_prefetched(
+    lavalink: &LavalinkClient,
+    guild_id: GuildId,
+    url: &str,
+) -> Option<TrackData> {
+    let runtime = lavalink.data::<LavalinkRuntimeData>().ok()?;
+    runtime
+        .prefetched
+        .remove_if(&guild_id, |_, (key, _)| key == url)
+        .map(|(_, (_, track))| track)
+}
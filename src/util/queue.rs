@@ -1,12 +1,92 @@
-use rand::RngExt;
+use poise::serenity_prelude::UserId;
+use rand::seq::SliceRandom;
 
-use crate::util::{config::MusicConfig, repeat::RepeatMode, track::TrackRequest};
-use std::collections::VecDeque;
+use crate::util::{
+    config::MusicConfig, filters::FilterPreset, music_ui::format_duration, repeat::RepeatMode,
+    track::TrackRequest,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    ops::Range,
+    time::Duration,
+};
+
+/// キューへ追加するときの制限（ギルド設定から作る）。
+#[derive(Clone, Debug, Default)]
+pub struct QueueLimits {
+    /// キュー全体の最大曲数
+    pub max_length: Option<usize>,
+    /// 1 人がキューに入れられる最大曲数
+    pub max_per_user: Option<usize>,
+    /// 曲の最大長（長さが分からない曲は制限しない）
+    pub max_duration: Option<Duration>,
+    /// 同じ URL の曲をキューに重複して入れられるか
+    pub allow_duplicates: bool,
+}
+
+impl QueueLimits {
+    /// キューの状態によらない、曲そのものに対する制限を確認する。
+    pub fn check_track(&self, req: &TrackRequest) -> Result<(), QueueError> {
+        match (self.max_duration, req.meta.duration) {
+            (Some(limit), Some(length)) if length > limit => {
+                Err(QueueError::TooLong { length, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// キューへの追加を断った理由。
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueueError {
+    Full { limit: usize },
+    UserLimit { limit: usize },
+    TooLong { length: Duration, limit: Duration },
+    Duplicate,
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full { limit } => write!(f, "キューがいっぱいです（上限 {limit} 曲）"),
+            Self::UserLimit { limit } => {
+                write!(f, "1 人がキューに入れられるのは {limit} 曲までです")
+            }
+            Self::TooLong { length, limit } => write!(
+                f,
+                "曲が長すぎます（{} / 上限 {}）",
+                format_duration(Some(*length)),
+                format_duration(Some(*limit))
+            ),
+            Self::Duplicate => write!(f, "同じ曲がすでにキューに入っています"),
+        }
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+/// 複数曲をまとめて追加した結果。
+#[derive(Debug, Default)]
+pub struct BatchOutcome {
+    pub added: usize,
+    /// 追加した最初の曲のキュー内の位置（1 始まり）
+    pub first_position: Option<usize>,
+    pub skipped: usize,
+    /// 最初に断った理由
+    pub reason: Option<QueueError>,
+}
 
 #[derive(Debug)]
 pub struct MusicQueue {
     pub queue: VecDeque<TrackRequest>,
     pub config: MusicConfig,
+    /// 次に再生する曲を先頭へ確定済みかどうか（先読み・割り込み用）
+    next_pinned: bool,
+    /// 再生したリクエスト者（最近再生した人ほど後ろ。公平モードでは前の人から順に回す）
+    served: Vec<UserId>,
+    /// 次に付ける `queue_order` の範囲（先頭追加は min を、末尾追加は max を伸ばす）
+    order_bounds: (i64, i64),
 }
 
 // Implement Default for MusicQueue
@@ -15,34 +95,193 @@ impl Default for MusicQueue {
         Self {
             queue: VecDeque::new(),
             config: MusicConfig::new(),
+            next_pinned: false,
+            served: Vec::new(),
+            order_bounds: (0, 0),
         }
     }
 }
 
 // MusicQueueのメソッドを実装
 impl MusicQueue {
-    /// 末尾に追加（一般ユーザー用）
-    pub fn push_back(&mut self, req: TrackRequest) {
+    /// 末尾に追加する（制限を確認しない。リピートや復元など内部用）
+    pub fn push_back(&mut self, mut req: TrackRequest) {
+        self.order_bounds.1 += 1;
+        req.queue_order = self.order_bounds.1;
+        self.queue.push_back(req);
+    }
+
+    /// 保存していた曲を末尾に戻す（再起動後の復元用）。
+    /// 保存時の `queue_order` があればそのまま使い、シャッフル解除で元の並びに戻せるようにする。
+    pub fn restore_back(&mut self, req: TrackRequest, keep_order: bool) {
+        if !keep_order {
+            self.push_back(req);
+            return;
+        }
+        self.order_bounds.0 = self.order_bounds.0.min(req.queue_order);
+        self.order_bounds.1 = self.order_bounds.1.max(req.queue_order);
         self.queue.push_back(req);
     }
 
-    /// 先頭に追加（管理者／優先再生用）
-    pub fn push_front(&mut self, req: TrackRequest) {
+    /// 追加してよいかを制限に照らして確認する。
+    pub fn check(&self, req: &TrackRequest, limits: &QueueLimits) -> Result<(), QueueError> {
+        if let Some(limit) = limits.max_length.filter(|&l| self.queue.len() >= l) {
+            return Err(QueueError::Full { limit });
+        }
+        if let Some(limit) = limits.max_per_user {
+            let queued = self
+                .queue
+                .iter()
+                .filter(|r| r.requested_by == req.requested_by)
+                .count();
+            if queued >= limit {
+                return Err(QueueError::UserLimit { limit });
+            }
+        }
+        limits.check_track(req)?;
+        if !limits.allow_duplicates {
+            let key = req.dedupe_key();
+            if self.queue.iter().any(|r| r.dedupe_key() == key) {
+                return Err(QueueError::Duplicate);
+            }
+        }
+        Ok(())
+    }
+
+    /// 制限を確認して末尾に追加し、キュー内の位置（1 始まり）を返す。
+    pub fn try_push_back(
+        &mut self,
+        req: TrackRequest,
+        limits: &QueueLimits,
+    ) -> Result<usize, QueueError> {
+        self.check(&req, limits)?;
+        self.push_back(req);
+        Ok(self.queue.len())
+    }
+
+    /// 制限を確認して先頭に追加する。
+    pub fn try_push_front(
+        &mut self,
+        req: TrackRequest,
+        limits: &QueueLimits,
+    ) -> Result<(), QueueError> {
+        self.check(&req, limits)?;
+        self.push_front(req);
+        Ok(())
+    }
+
+    /// 制限の範囲内で順に末尾へ追加する（プレイリスト用）。断った曲は飛ばす。
+    pub fn extend_checked(
+        &mut self,
+        reqs: impl IntoIterator<Item = TrackRequest>,
+        limits: &QueueLimits,
+    ) -> BatchOutcome {
+        let mut outcome = BatchOutcome::default();
+        for req in reqs {
+            match self.try_push_back(req, limits) {
+                Ok(position) => {
+                    outcome.added += 1;
+                    outcome.first_position.get_or_insert(position);
+                }
+                Err(err) => {
+                    outcome.skipped += 1;
+                    outcome.reason.get_or_insert(err);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// 先頭に追加（管理者／優先再生用）。シャッフル・公平モードでも次に再生する。
+    pub fn push_front(&mut self, mut req: TrackRequest) {
+        self.order_bounds.0 -= 1;
+        req.queue_order = self.order_bounds.0;
         self.queue.push_front(req);
+        self.next_pinned = true;
     }
 
-    /// 次に再生する曲（front）を取り出す
-    pub fn pop_next(&mut self) -> Option<TrackRequest> {
-        if self.config.shuffle {
-            let len = self.queue.len();
-            if len == 0 {
-                return None;
+    /// 次に再生する曲を参照する。公平モードではここで選んだ曲を先頭に確定させる。
+    pub fn peek_next(&mut self) -> Option<&TrackRequest> {
+        if self.config.fair && !self.next_pinned && self.queue.len() > 1 {
+            if let Some(idx) = self.order_indices().first().copied() {
+                if let Some(req) = self.queue.remove(idx) {
+                    self.queue.push_front(req);
+                }
+                self.next_pinned = true;
             }
-            let idx = rand::rng().random_range(0..len);
+        }
+        self.queue.front()
+    }
+
+    /// 次に再生する曲を取り出す
+    pub fn pop_next(&mut self) -> Option<TrackRequest> {
+        // シャッフル時はキュー自体が並べ替え済みなので先頭から取る
+        let req = if std::mem::take(&mut self.next_pinned) {
+            self.queue.pop_front()
+        } else if self.config.fair {
+            let idx = self.order_indices().first().copied()?;
             self.queue.remove(idx)
         } else {
             self.queue.pop_front()
+        }?;
+        let queue = &self.queue;
+        self.served
+            .retain(|u| *u != req.requested_by && queue.iter().any(|r| r.requested_by == *u));
+        self.served.push(req.requested_by);
+        Some(req)
+    }
+
+    /// 公平モードの再生順（キュー内の添字）。
+    /// リクエスト者ごとに並べ、しばらく再生していない人から 1 曲ずつ順番に取り出す。
+    fn fair_order(&self) -> Vec<usize> {
+        let head = self.queue.front().filter(|_| self.next_pinned);
+        let mut lanes: Vec<(UserId, VecDeque<usize>)> = Vec::new();
+        for (idx, req) in self.queue.iter().enumerate().skip(head.map_or(0, |_| 1)) {
+            match lanes.iter_mut().find(|(user, _)| *user == req.requested_by) {
+                Some((_, lane)) => lane.push_back(idx),
+                None => lanes.push((req.requested_by, VecDeque::from([idx]))),
+            }
         }
+        // 確定済みの次曲のリクエスト者は、最後に再生した人として扱う
+        let head_user = head.map(|req| req.requested_by);
+        lanes.sort_by_key(|(user, _)| {
+            if Some(*user) == head_user {
+                Some(usize::MAX)
+            } else {
+                self.served.iter().position(|u| u == user)
+            }
+        });
+
+        let mut order = Vec::with_capacity(self.queue.len());
+        order.extend(head.map(|_| 0));
+        while order.len() < self.queue.len() {
+            for (_, lane) in lanes.iter_mut() {
+                order.extend(lane.pop_front());
+            }
+        }
+        order
+    }
+
+    /// 実際に再生される順のキュー内の添字。
+    fn order_indices(&self) -> Vec<usize> {
+        if self.config.fair {
+            self.fair_order()
+        } else {
+            (0..self.queue.len()).collect()
+        }
+    }
+
+    /// 実際に再生される順に並べた曲（`/queue` の表示用）。
+    pub fn play_order(&self) -> Vec<&TrackRequest> {
+        self.order_indices()
+            .into_iter()
+            .map(|idx| &self.queue[idx])
+            .collect()
+    }
+
+    /// 再生順での位置（0 始まり）をキュー内の添字に変換する。
+    pub fn index_at(&self, position: usize) -> Option<usize> {
+        self.order_indices().get(position).copied()
     }
 
     /// 参照イテレータ（cloneしない）
@@ -51,19 +290,396 @@ impl MusicQueue {
     }
     pub fn remove_at(&mut self, idx: usize) -> Option<TrackRequest> {
         if idx < self.queue.len() {
+            if idx == 0 {
+                self.next_pinned = false;
+            }
             self.queue.remove(idx)
         } else {
             None
         }
     }
+    /// キュー内の `from` 番目の曲を `to` 番目へ移動する（いずれも 0 始まりの添字）。
+    pub fn move_item(&mut self, from: usize, to: usize) -> Option<&TrackRequest> {
+        if from >= self.queue.len() || to >= self.queue.len() {
+            return None;
+        }
+        if from == 0 || to == 0 {
+            self.next_pinned = false;
+        }
+        let req = self.queue.remove(from)?;
+        self.queue.insert(to, req);
+        self.queue.get(to)
+    }
+
+    /// 2 曲の位置を入れ替える（0 始まりの添字）。
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        if a >= self.queue.len() || b >= self.queue.len() {
+            return false;
+        }
+        if a == 0 || b == 0 {
+            self.next_pinned = false;
+        }
+        self.queue.swap(a, b);
+        true
+    }
+
+    /// すべて削除し、削除した件数を返す。
+    pub fn clear(&mut self) -> usize {
+        self.next_pinned = false;
+        let removed = self.queue.len();
+        self.queue.clear();
+        removed
+    }
+
+    /// 同じ曲（YouTube は動画 ID で判定）を先に入っている 1 曲だけ残して削除し、削除した件数を返す。
+    pub fn dedupe(&mut self) -> usize {
+        let before = self.queue.len();
+        let mut seen = HashSet::new();
+        self.queue.retain(|r| seen.insert(r.dedupe_key()));
+        before - self.queue.len()
+    }
+
+    /// 再生順での位置の範囲（0 始まり）にある曲を削除して返す。
+    pub fn remove_range(&mut self, range: Range<usize>) -> Vec<TrackRequest> {
+        let order = self.order_indices();
+        let Some(targets) = order.get(range.start..range.end.min(order.len())) else {
+            return Vec::new();
+        };
+        self.remove_indices(targets.to_vec())
+    }
+
+    /// 指定したユーザーがリクエストした曲をすべて削除して返す。
+    pub fn remove_by_user(&mut self, user: UserId) -> Vec<TrackRequest> {
+        let targets = self
+            .queue
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.requested_by == user)
+            .map(|(idx, _)| idx)
+            .collect();
+        self.remove_indices(targets)
+    }
+
+    /// 添字の集合をまとめて削除し、キューの並び順で返す。
+    fn remove_indices(&mut self, mut targets: Vec<usize>) -> Vec<TrackRequest> {
+        targets.sort_unstable();
+        targets.dedup();
+        if targets.first() == Some(&0) {
+            self.next_pinned = false;
+        }
+        let mut removed: Vec<_> = targets
+            .into_iter()
+            .rev()
+            .filter_map(|idx| self.queue.remove(idx))
+            .collect();
+        removed.reverse();
+        removed
+    }
+
     /// キューの長さ
     pub fn len(&self) -> usize {
         self.queue.len()
     }
     pub fn set_repeat_mode(&mut self, mode: RepeatMode) {
-        self.config.repeat_mode = mode;
+        self.config.repeat_mode = Some(mode);
     }
+    /// シャッフルを切り替える。ON にしたときにキューを並べ替え、OFF に戻すまでその順番を保つ。
+    /// OFF にすると元の並び（シャッフル中に追加した曲はその後ろ）に戻す。
     pub fn set_shuffle(&mut self, on: bool) {
+        if on == self.config.shuffle {
+            return;
+        }
         self.config.shuffle = on;
+        if on {
+            self.config.fair = false;
+            // 今の並びを「元の並び」として記録し直す（移動・入れ替えの結果を保つため）
+            for (i, req) in self.queue.iter_mut().enumerate() {
+                req.queue_order = i as i64;
+            }
+            self.order_bounds = (0, self.queue.len() as i64);
+            // 割り込みで確定済みの次曲はそのまま先頭に残す
+            let start = usize::from(self.next_pinned);
+            let items = self.queue.make_contiguous();
+            if let Some(rest) = items.get_mut(start..) {
+                rest.shuffle(&mut rand::rng());
+            }
+        } else {
+            self.queue
+                .make_contiguous()
+                .sort_by_key(|req| req.queue_order);
+            self.next_pinned = false;
+        }
+    }
+    /// 公平モード（リクエスト者ごとに順番に再生）を切り替える。シャッフルとは併用しない。
+    pub fn set_fair(&mut self, on: bool) {
+        if on {
+            self.set_shuffle(false);
+        }
+        self.config.fair = on;
+        self.next_pinned = false;
+    }
+    pub fn set_autoplay(&mut self, on: bool) {
+        self.config.autoplay = on;
+    }
+    pub fn set_crossfade(&mut self, secs: u64) {
+        self.config.crossfade_secs = secs;
+    }
+    pub fn set_volume(&mut self, volume: u16) {
+        self.config.volume = Some(volume);
+    }
+    pub fn set_filter(&mut self, preset: FilterPreset, custom_eq: Vec<f64>) {
+        self.config.filter = preset;
+        self.config.custom_eq = custom_eq;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(url: &str, user: u64) -> TrackRequest {
+        TrackRequest::new(url.to_string(), UserId::new(user))
+    }
+
+    fn queue_of(urls: &[&str]) -> MusicQueue {
+        let mut q = MusicQueue::default();
+        for url in urls {
+            q.push_back(track(url, 1));
+        }
+        q
+    }
+
+    fn urls(q: &MusicQueue) -> Vec<&str> {
+        q.iter().map(|r| r.url.as_str()).collect()
+    }
+
+    #[test]
+    fn move_item_out_of_range() {
+        let mut q = queue_of(&["a", "b", "c"]);
+        assert!(q.move_item(3, 0).is_none());
+        assert!(q.move_item(0, 3).is_none());
+        assert_eq!(urls(&q), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn move_item_reorders() {
+        let mut q = queue_of(&["a", "b", "c"]);
+        assert_eq!(q.move_item(2, 1).map(|r| r.url.as_str()), Some("c"));
+        assert_eq!(urls(&q), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn move_item_touching_head_clears_pin() {
+        let mut q = queue_of(&["a", "b"]);
+        q.push_front(track("pinned", 1));
+        assert!(q.next_pinned);
+        q.move_item(2, 0);
+        assert!(!q.next_pinned);
+
+        q.push_front(track("pinned2", 1));
+        q.move_item(0, 2);
+        assert!(!q.next_pinned);
+    }
+
+    #[test]
+    fn swap_out_of_range() {
+        let mut q = queue_of(&["a", "b"]);
+        assert!(!q.swap(0, 2));
+        assert!(!q.swap(5, 1));
+        assert_eq!(urls(&q), ["a", "b"]);
+    }
+
+    #[test]
+    fn swap_touching_head_clears_pin() {
+        let mut q = queue_of(&["a", "b"]);
+        q.push_front(track("pinned", 1));
+        assert!(q.swap(0, 2));
+        assert!(!q.next_pinned);
+        assert_eq!(urls(&q), ["b", "a", "pinned"]);
+    }
+
+    #[test]
+    fn swap_without_head_keeps_pin() {
+        let mut q = queue_of(&["a", "b"]);
+        q.push_front(track("pinned", 1));
+        assert!(q.swap(1, 2));
+        assert!(q.next_pinned);
+    }
+
+    #[test]
+    fn clear_returns_count() {
+        let mut q = queue_of(&["a", "b", "c"]);
+        assert_eq!(q.clear(), 3);
+        assert_eq!(q.len(), 0);
+    }
+
+    #[test]
+    fn dedupe_treats_youtube_url_forms_as_same() {
+        let mut q = queue_of(&[
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://example.com/a.mp3",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://example.com/a.mp3",
+        ]);
+        assert_eq!(q.dedupe(), 2);
+        assert_eq!(
+            urls(&q),
+            [
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                "https://example.com/a.mp3"
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_range_clamps_end() {
+        let mut q = queue_of(&["a", "b", "c"]);
+        let removed = q.remove_range(1..10);
+        assert_eq!(
+            removed.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            ["b", "c"]
+        );
+        assert_eq!(urls(&q), ["a"]);
+        assert!(q.remove_range(5..8).is_empty());
+    }
+
+    #[test]
+    fn remove_range_follows_fair_order() {
+        let mut q = MusicQueue::default();
+        q.push_back(track("a1", 1));
+        q.push_back(track("a2", 1));
+        q.push_back(track("b1", 2));
+        q.set_fair(true);
+        // 公平モードの再生順は a1, b1, a2
+        let order: Vec<_> = q.play_order().iter().map(|r| r.url.as_str()).collect();
+        assert_eq!(order, ["a1", "b1", "a2"]);
+
+        let removed = q.remove_range(1..2);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].url, "b1");
+        assert_eq!(urls(&q), ["a1", "a2"]);
+    }
+
+    #[test]
+    fn remove_by_user_keeps_others() {
+        let mut q = MusicQueue::default();
+        q.push_back(track("a1", 1));
+        q.push_back(track("b1", 2));
+        q.push_back(track("a2", 1));
+        let removed = q.remove_by_user(UserId::new(1));
+        assert_eq!(
+            removed.iter().map(|r| r.url.as_str()).collect::<Vec<_>>(),
+            ["a1", "a2"]
+        );
+        assert_eq!(urls(&q), ["b1"]);
+    }
+
+    #[test]
+    fn unshuffle_restores_original_order() {
+        let mut q = queue_of(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        q.set_shuffle(true);
+        q.set_shuffle(false);
+        assert_eq!(urls(&q), ["a", "b", "c", "d", "e", "f", "g", "h"]);
+    }
+
+    #[test]
+    fn tracks_added_while_shuffled_surround_original_order() {
+        let mut q = queue_of(&["a", "b", "c"]);
+        q.set_shuffle(true);
+        q.push_back(track("back", 1));
+        q.push_front(track("front", 1));
+        q.set_shuffle(false);
+        assert_eq!(urls(&q), ["front", "a", "b", "c", "back"]);
+    }
+
+    #[test]
+    fn shuffle_keeps_pinned_head() {
+        let mut q = queue_of(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        q.push_front(track("pinned", 1));
+        q.set_shuffle(true);
+        assert!(q.next_pinned);
+        assert_eq!(q.peek_next().map(|r| r.url.as_str()), Some("pinned"));
+        assert_eq!(q.len(), 9);
+    }
+
+    #[test]
+    fn restored_tracks_unshuffle_by_saved_order() {
+        let mut q = MusicQueue::default();
+        q.set_shuffle(true);
+        for (url, order) in [("c", 2), ("a", 0), ("b", 1)] {
+            let mut req = track(url, 1);
+            req.queue_order = order;
+            q.restore_back(req, true);
+        }
+        q.push_back(track("d", 1));
+        q.set_shuffle(false);
+        assert_eq!(urls(&q), ["a", "b", "c", "d"]);
+    }
+
+    fn fair_queue(tracks: &[(&str, u64)]) -> MusicQueue {
+        let mut q = MusicQueue::default();
+        for (url, user) in tracks {
+            q.push_back(track(url, *user));
+        }
+        q.set_fair(true);
+        q
+    }
+
+    fn play_urls(q: &MusicQueue) -> Vec<&str> {
+        q.play_order().iter().map(|r| r.url.as_str()).collect()
+    }
+
+    #[test]
+    fn fair_order_interleaves_requesters() {
+        let q = fair_queue(&[
+            ("a1", 1),
+            ("a2", 1),
+            ("a3", 1),
+            ("b1", 2),
+            ("b2", 2),
+            ("c1", 3),
+        ]);
+        assert_eq!(play_urls(&q), ["a1", "b1", "c1", "a2", "b2", "a3"]);
+    }
+
+    #[test]
+    fn fair_order_resumes_after_last_requester() {
+        let mut q = fair_queue(&[("a1", 1), ("a2", 1), ("b1", 2), ("b2", 2), ("c1", 3)]);
+        assert_eq!(q.pop_next().map(|r| r.url), Some("a1".to_string()));
+        assert_eq!(play_urls(&q), ["b1", "c1", "a2", "b2"]);
+        assert_eq!(q.pop_next().map(|r| r.url), Some("b1".to_string()));
+        assert_eq!(play_urls(&q), ["c1", "a2", "b2"]);
+    }
+
+    #[test]
+    fn fair_push_front_plays_next_then_rotation_resumes() {
+        let mut q = fair_queue(&[("a1", 1), ("a2", 1), ("b1", 2), ("b2", 2)]);
+        assert_eq!(q.pop_next().map(|r| r.url), Some("a1".to_string()));
+        q.push_front(track("urgent", 2));
+        assert_eq!(play_urls(&q), ["urgent", "a2", "b1", "b2"]);
+        assert_eq!(q.pop_next().map(|r| r.url), Some("urgent".to_string()));
+        // 割り込んだ曲がリクエスト者 2 の番として数えられる
+        assert_eq!(play_urls(&q), ["a2", "b1", "b2"]);
+    }
+
+    #[test]
+    fn fair_play_order_matches_pop_sequence() {
+        let mut q = fair_queue(&[
+            ("a1", 1),
+            ("b1", 2),
+            ("a2", 1),
+            ("c1", 3),
+            ("a3", 1),
+            ("b2", 2),
+        ]);
+        let expected: Vec<String> = play_urls(&q).into_iter().map(String::from).collect();
+        let mut popped = Vec::new();
+        while let Some(next) = q.peek_next().map(|r| r.url.clone()) {
+            // peek_next で先頭に確定しても順番は変わらない
+            let req = q.pop_next().expect("peeked track");
+            assert_eq!(req.url, next);
+            popped.push(req.url);
+        }
+        assert_eq!(popped, expected);
     }
 }
@@ -1,7 +1,9 @@
 use poise::ChoiceParameter;
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, ChoiceParameter)]
+#[derive(Copy, Clone, Debug, Default, ChoiceParameter, Serialize, Deserialize)]
 pub enum RepeatMode {
+    #[default]
     #[name = "Off"]
     Off,
     #[name = "Track"]
@@ -0,0 +1,229 @@
+use std::{sync::Arc, time::Duration};
+
+use lavalink_rs::client::LavalinkClient;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, Colour, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateMessage, EditMessage, GuildId, Mentionable,
+};
+
+use crate::util::{
+    alias::Error,
+    lavalink_player::{
+        LavalinkRuntimeData, ensure_player_for_connection, play_track_req_lavalink_at,
+    },
+    music_ui::{format_duration, track_embed},
+    player::ManualTransitionGuard,
+    storage::ResumeSession,
+    track::TrackRequest,
+};
+
+const ACCENT: Colour = Colour::new(0x5865F2);
+const SUCCESS: Colour = Colour::new(0x2ECC71);
+const DANGER: Colour = Colour::new(0xE74C3C);
+const RESUME_OFFER_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// 保存済みのボイスチャンネルへ再接続し、記録していた位置からトラックを再開する。
+pub async fn resume_session(
+    lavalink: Arc<LavalinkClient>,
+    session: &ResumeSession,
+) -> Result<TrackRequest, Error> {
+    let runtime = lavalink
+        .data::<LavalinkRuntimeData>()
+        .map_err(|e| Error::from(format!("failed to fetch lavalink runtime data: {e}")))?;
+    let guild_id = session.guild_id;
+
+    let (connection, _call) = runtime
+        .songbird
+        .join_gateway(guild_id, session.voice_channel)
+        .await?;
+    ensure_player_for_connection(&lavalink, guild_id, connection).await?;
+
+    // 復元時にキューへ戻したトラックと、履歴末尾の重複を取り除いてから再生する。
+    if let Some(mut q) = runtime.queues.get_mut(&guild_id) {
+        if let Some(idx) = q.iter().position(|t| t.url == session.track.url) {
+            q.remove_at(idx);
+        }
+    }
+    if let Some(mut h) = runtime.history.get_mut(&guild_id) {
+        if h.back().is_some_and(|t| t.url == session.track.url) {
+            h.pop_back();
+        }
+    }
+
+    play_track_req_lavalink_at(
+        guild_id,
+        lavalink.clone(),
+        runtime.lavalink_playing.clone(),
+        runtime.history.clone(),
+        session.track.clone(),
+        Some(session.position),
+    )
+    .await
+}
+
+/// 現在のボイス接続で Lavalink プレイヤーを作り直し、記録した位置から再生中のトラックを再開する。
+/// ノード障害や Lavalink 再起動でプレイヤーが失われたときに使う。
+pub async fn recreate_player(
+    lavalink: Arc<LavalinkClient>,
+    guild_id: GuildId,
+) -> Result<Option<TrackRequest>, Error> {
+    let runtime = lavalink
+        .data::<LavalinkRuntimeData>()
+        .map_err(|e| Error::from(format!("failed to fetch lavalink runtime data: {e}")))?;
+    let Some(call) = runtime.songbird.get(guild_id) else {
+        return Ok(None);
+    };
+    let Some(connection) = call.lock().await.current_connection().cloned() else {
+        return Ok(None);
+    };
+
+    let _guard = ManualTransitionGuard::acquire(&runtime.transition_flags, guild_id);
+    // 応答しないノード上のプレイヤーはローカルの登録だけ外せれば十分なので、エラーは無視する。
+    let _ = lavalink.delete_player(guild_id).await;
+    ensure_player_for_connection(&lavalink, guild_id, connection).await?;
+
+    let Some(track) = runtime
+        .lavalink_playing
+        .get(&guild_id)
+        .map(|e| e.value().clone())
+    else {
+        return Ok(None);
+    };
+    let position = runtime
+        .positions
+        .get(&guild_id)
+        .map(|p| Duration::from_millis(*p));
+    if let Some(mut h) = runtime.history.get_mut(&guild_id) {
+        if h.back().is_some_and(|t| t.url == track.url) {
+            h.pop_back();
+        }
+    }
+
+    play_track_req_lavalink_at(
+        guild_id,
+        lavalink.clone(),
+        runtime.lavalink_playing.clone(),
+        runtime.history.clone(),
+        track,
+        position,
+    )
+    .await
+    .map(Some)
+}
+
+/// 再起動前に再生していたギルドへ、続きから再開するかを尋ねるメッセージを送る。
+pub fn offer_resume(
+    ctx: serenity::Context,
+    lavalink: Arc<LavalinkClient>,
+    sessions: Vec<ResumeSession>,
+) {
+    let settings = lavalink
+        .data::<LavalinkRuntimeData>()
+        .ok()
+        .map(|runtime| runtime.settings.clone());
+    for session in sessions {
+        // 通知チャンネルが設定されていれば、前回のパネルのチャンネルより優先する
+        let announce = settings
+            .as_ref()
+            .and_then(|s| s.get(session.guild_id).announce_channel());
+        let Some(text_channel) = announce.or(session.text_channel) else {
+            tracing::info!(guild = %session.guild_id, "no panel channel recorded; skip resume offer");
+            continue;
+        };
+        let ctx = ctx.clone();
+        let lavalink = lavalink.clone();
+        tokio::spawn(async move {
+            if let Err(err) = run_offer(&ctx, lavalink, text_channel, &session).await {
+                tracing::warn!(guild = %session.guild_id, error = %err, "resume offer failed");
+            }
+        });
+    }
+}
+
+async fn run_offer(
+    ctx: &serenity::Context,
+    lavalink: Arc<LavalinkClient>,
+    text_channel: serenity::ChannelId,
+    session: &ResumeSession,
+) -> Result<(), Error> {
+    let embed = track_embed(
+        "⏯ 前回の再生を再開できます",
+        Some(&session.track),
+        Some(format!(
+            "{} の {} から再開します。",
+            session.voice_channel.mention(),
+            format_duration(Some(session.position))
+        )),
+        ACCENT,
+    );
+    let components = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("resume_session_accept")
+            .label("▶ 再開")
+            .style(ButtonStyle::Success),
+        CreateButton::new("resume_session_dismiss")
+            .label("✖ 破棄")
+            .style(ButtonStyle::Secondary),
+    ])];
+    let mut msg = text_channel
+        .send_message(
+            &ctx.http,
+            CreateMessage::new().embed(embed).components(components),
+        )
+        .await?;
+
+    let Some(interaction) = msg
+        .await_component_interaction(ctx)
+        .timeout(RESUME_OFFER_TIMEOUT)
+        .await
+    else {
+        let _ = msg
+            .edit(ctx, EditMessage::new().components(Vec::new()))
+            .await;
+        return Ok(());
+    };
+    let _ = interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await;
+
+    if interaction.data.custom_id != "resume_session_accept" {
+        let embed = track_embed(
+            "⏹ 再開を取り消しました",
+            Some(&session.track),
+            Some("キューは保持されています。`/play` で再生できます。".into()),
+            ACCENT,
+        );
+        let _ = msg
+            .edit(ctx, EditMessage::new().embed(embed).components(Vec::new()))
+            .await;
+        return Ok(());
+    }
+
+    let embed = match resume_session(lavalink.clone(), session).await {
+        Ok(started) => {
+            if let Ok(runtime) = lavalink.data::<LavalinkRuntimeData>() {
+                runtime
+                    .now_playing
+                    .insert(session.guild_id, (msg.channel_id, msg.id));
+            }
+            track_embed(
+                "🎵 再生を再開しました",
+                Some(&started),
+                Some(format!(
+                    "{} から再開しました。",
+                    format_duration(Some(session.position))
+                )),
+                SUCCESS,
+            )
+        }
+        Err(err) => track_embed(
+            "❌ 再開に失敗しました",
+            Some(&session.track),
+            Some(err.to_string()),
+            DANGER,
+        ),
+    };
+    let _ = msg
+        .edit(ctx, EditMessage::new().embed(embed).components(Vec::new()))
+        .await;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use chrono::Utc;
+use poise::ChoiceParameter;
+use poise::serenity_prelude::{GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    StorageSettings,
+    util::{
+        alias::Error,
+        playlist,
+        storage::{StoredTrack, storage_dir, write_json_atomic},
+        track::TrackRequest,
+    },
+};
+
+/// 1 人（1 サーバー）あたりに保存できるプレイリスト数。
+pub const MAX_PLAYLISTS: usize = 50;
+/// 1 つのプレイリストに入れられる曲数。
+pub const MAX_PLAYLIST_TRACKS: usize = 500;
+const MAX_NAME_CHARS: usize = 50;
+
+/// プレイリストの保存先。
+#[derive(Copy, Clone, Debug, Default, PartialEq, ChoiceParameter)]
+pub enum PlaylistScope {
+    #[default]
+    #[name = "自分用"]
+    Personal,
+    #[name = "サーバー共有"]
+    Guild,
+}
+
+/// プレイリストの持ち主（ユーザー個人、またはサーバー）。
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PlaylistOwner {
+    User(UserId),
+    Guild(GuildId),
+}
+
+impl PlaylistOwner {
+    pub fn new(scope: PlaylistScope, user: UserId, guild: GuildId) -> Self {
+        match scope {
+            PlaylistScope::Personal => Self::User(user),
+            PlaylistScope::Guild => Self::Guild(guild),
+        }
+    }
+
+    fn file_name(self) -> String {
+        match self {
+            Self::User(id) => format!("user_{id}.json"),
+            Self::Guild(id) => format!("guild_{id}.json"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedPlaylist {
+    pub name: String,
+    /// 作成したユーザー
+    pub created_by: u64,
+    /// 最終更新（RFC 3339）
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub tracks: Vec<StoredTrack>,
+}
+
+impl SavedPlaylist {
+    pub fn new(name: String, created_by: UserId, tracks: Vec<StoredTrack>) -> Self {
+        Self {
+            name,
+            created_by: created_by.get(),
+            updated_at: Utc::now().to_rfc3339(),
+            tracks,
+        }
+    }
+
+    /// 読み込む人をリクエスト者とした TrackRequest に変換する。
+    pub fn requests(&self, requested_by: UserId) -> Vec<TrackRequest> {
+        self.tracks
+            .iter()
+            .cloned()
+            .map(|st| {
+                let mut req = TrackRequest::from(st);
+                req.requested_by = requested_by;
+                req
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// 拡張 M3U として書き出す。
+    pub fn to_m3u(&self) -> String {
+        let mut out = String::from("#EXTM3U\n");
+        out.push_str(&format!("#PLAYLIST:{}\n", self.name));
+        for t in &self.tracks {
+            let secs = t.duration_ms.map(|ms| (ms / 1000) as i64).unwrap_or(-1);
+            let title = match (&t.artist, &t.title) {
+                (Some(artist), Some(title)) => format!("{artist} - {title}"),
+                (None, Some(title)) => title.clone(),
+                _ => t.url.clone(),
+            };
+            out.push_str(&format!("#EXTINF:{secs},{title}\n{}\n", t.url));
+        }
+        out
+    }
+
+    /// JSON（このボットの形式、または URL の配列）か M3U を読み込む。
+    pub fn parse(bytes: &[u8], name: String, created_by: UserId) -> Result<Self, Error> {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| Error::from("ファイルが UTF-8 のテキストではありません"))?;
+        let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+        let tracks = if trimmed.starts_with('{') {
+            serde_json::from_str::<SavedPlaylist>(trimmed)?.tracks
+        } else if trimmed.starts_with('[') {
+            serde_json::from_str::<Vec<String>>(trimmed)?
+                .into_iter()
+                .map(|url| stored_from_url(url, created_by))
+                .collect()
+        } else {
+            parse_m3u(trimmed, created_by)
+        };
+        if tracks.is_empty() {
+            return Err(Error::from("ファイルに曲が見つかりませんでした"));
+        }
+        let mut playlist = Self::new(name, created_by, tracks);
+        playlist.tracks.truncate(MAX_PLAYLIST_TRACKS);
+        Ok(playlist)
+    }
+}
+
+fn stored_from_url(url: String, requested_by: UserId) -> StoredTrack {
+    StoredTrack::from(&TrackRequest::new(url, requested_by))
+}
+
+fn parse_m3u(text: &str, requested_by: UserId) -> Vec<StoredTrack> {
+    playlist::parse_m3u(text, None)
+        .into_iter()
+        .map(|(url, title, duration)| {
+            let mut track = stored_from_url(url, requested_by);
+            track.title = title;
+            track.duration_ms = duration.map(|d| d.as_millis() as u64);
+            track
+        })
+        .collect()
+}
+
+/// プレイリスト名として使える形に整える。
+pub fn normalize_name(name: &str) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::from("プレイリスト名を指定してください"));
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(Error::from(format!(
+            "プレイリスト名は {MAX_NAME_CHARS} 文字以内にしてください"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// 保存済みプレイリストを `data/playlists/` に持ち主ごとの JSON として保存する。
+pub struct PlaylistStore {
+    dir: Option<PathBuf>,
+    /// 読み込み〜書き込みの間に他の更新が割り込まないようにする
+    lock: Mutex<()>,
+}
+
+impl PlaylistStore {
+    pub fn new(cfg: Option<&StorageSettings>) -> Self {
+        Self {
+            dir: storage_dir(cfg).map(|d| d.join("playlists")),
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn read(&self, owner: PlaylistOwner) -> Result<BTreeMap<String, SavedPlaylist>, Error> {
+        let Some(dir) = &self.dir else {
+            return Err(Error::from("データの保存が無効になっているため使えません"));
+        };
+        match tokio::fs::read(dir.join(owner.file_name())).await {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// 名前順のプレイリスト一覧。
+    pub async fn list(&self, owner: PlaylistOwner) -> Result<Vec<SavedPlaylist>, Error> {
+        Ok(self.read(owner).await?.into_values().collect())
+    }
+
+    pub async fn get(&self, owner: PlaylistOwner, name: &str) -> Result<SavedPlaylist, Error> {
+        self.read(owner)
+            .await?
+            .remove(name)
+            .ok_or_else(|| Error::from(format!("プレイリスト「{name}」は見つかりませんでした")))
+    }
+
+    /// プレイリスト群を変更して保存する。`f` の返り値をそのまま返す。
+    pub async fn update<T>(
+        &self,
+        owner: PlaylistOwner,
+        f: impl FnOnce(&mut BTreeMap<String, SavedPlaylist>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let _guard = self.lock.lock().await;
+        let mut playlists = self.read(owner).await?;
+        let out = f(&mut playlists)?;
+        let Some(dir) = &self.dir else {
+            return Err(Error::from("データの保存が無効になっているため使えません"));
+        };
+        let path = dir.join(owner.file_name());
+        if playlists.is_empty() {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            tokio::fs::create_dir_all(dir).await?;
+            write_json_atomic(&path, &playlists).await?;
+        }
+        Ok(out)
+    }
+
+    /// 新しいプレイリストを保存する（同名があれば上書き）。上書きしたら true。
+    pub async fn save(&self, owner: PlaylistOwner, playlist: SavedPlaylist) -> Result<bool, Error> {
+        self.update(owner, |playlists| {
+            if !playlists.contains_key(&playlist.name) && playlists.len() >= MAX_PLAYLISTS {
+                return Err(Error::from(format!(
+                    "保存できるプレイリストは {MAX_PLAYLISTS} 件までです"
+                )));
+            }
+            Ok(playlists.insert(playlist.name.clone(), playlist).is_some())
+        })
+        .await
+    }
+}
@@ -0,0 +1,197 @@
+use std::{fmt, str::FromStr, sync::RwLock};
+
+use lavalink_rs::{
+    client::LavalinkClient,
+    model::track::{TrackData, TrackLoadData},
+};
+use poise::serenity_prelude::{AutocompleteChoice, GuildId};
+use serde::{Deserialize, Serialize};
+
+use crate::util::alias::{Context, Error};
+
+/// 検索語をどのサービスで検索するか（Lavalink の検索プレフィックス）。
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchSource {
+    #[default]
+    YouTube,
+    YouTubeMusic,
+    SoundCloud,
+    /// 以下は LavaSrc などのプラグインが必要
+    Spotify,
+    AppleMusic,
+    Deezer,
+    YandexMusic,
+}
+
+impl SearchSource {
+    pub const ALL: [Self; 7] = [
+        Self::YouTube,
+        Self::YouTubeMusic,
+        Self::SoundCloud,
+        Self::Spotify,
+        Self::AppleMusic,
+        Self::Deezer,
+        Self::YandexMusic,
+    ];
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::YouTube => "ytsearch",
+            Self::YouTubeMusic => "ytmsearch",
+            Self::SoundCloud => "scsearch",
+            Self::Spotify => "spsearch",
+            Self::AppleMusic => "amsearch",
+            Self::Deezer => "dzsearch",
+            Self::YandexMusic => "ymsearch",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::YouTube => "YouTube",
+            Self::YouTubeMusic => "YouTube Music",
+            Self::SoundCloud => "SoundCloud",
+            Self::Spotify => "Spotify",
+            Self::AppleMusic => "Apple Music",
+            Self::Deezer => "Deezer",
+            Self::YandexMusic => "Yandex Music",
+        }
+    }
+
+    /// `/v4/info` の `sourceManagers` に現れる名前。
+    fn source_manager(self) -> &'static str {
+        match self {
+            Self::YouTube | Self::YouTubeMusic => "youtube",
+            Self::SoundCloud => "soundcloud",
+            Self::Spotify => "spotify",
+            Self::AppleMusic => "applemusic",
+            Self::Deezer => "deezer",
+            Self::YandexMusic => "yandexmusic",
+        }
+    }
+
+    pub fn query(self, terms: &str) -> String {
+        format!("{}:{}", self.prefix(), terms.trim())
+    }
+
+    /// Lavalink で使えるか。ノードの情報が取れていなければ標準のソースだけを使える扱いにする。
+    pub fn is_available(self) -> bool {
+        let caps = CAPABILITIES.read().unwrap_or_else(|e| e.into_inner());
+        match caps.as_ref() {
+            Some(caps) => caps
+                .source_managers
+                .iter()
+                .any(|m| m.eq_ignore_ascii_case(self.source_manager())),
+            None => matches!(self, Self::YouTube | Self::YouTubeMusic | Self::SoundCloud),
+        }
+    }
+
+    pub fn available() -> Vec<Self> {
+        Self::ALL.into_iter().filter(|s| s.is_available()).collect()
+    }
+}
+
+#[derive(Debug)]
+pub struct UnknownSearchSource;
+
+impl fmt::Display for UnknownSearchSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("検索ソースは `ytsearch` / `ytmsearch` / `scsearch` などで指定してください")
+    }
+}
+
+impl std::error::Error for UnknownSearchSource {}
+
+/// プレフィックス（`ytsearch` など）だけを受け付ける。
+/// プレフィックスコマンドで検索語の先頭をソース名と誤認しないようにするため。
+impl FromStr for SearchSource {
+    type Err = UnknownSearchSource;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_end_matches(':');
+        Self::ALL
+            .into_iter()
+            .find(|src| src.prefix().eq_ignore_ascii_case(s))
+            .ok_or(UnknownSearchSource)
+    }
+}
+
+/// `/v4/info` から分かった、Lavalink で使えるソースとプラグイン。
+#[derive(Clone, Debug, Default)]
+pub struct LavalinkCapabilities {
+    pub source_managers: Vec<String>,
+    pub plugins: Vec<String>,
+}
+
+static CAPABILITIES: RwLock<Option<LavalinkCapabilities>> = RwLock::new(None);
+
+/// LavaSrc プラグインが読み込まれているか。ノードの情報が取れていなければ `None`。
+pub fn lavasrc_loaded() -> Option<bool> {
+    let caps = CAPABILITIES.read().unwrap_or_else(|e| e.into_inner());
+    caps.as_ref().map(|caps| {
+        caps.plugins
+            .iter()
+            .any(|p| p.to_ascii_lowercase().contains("lavasrc"))
+    })
+}
+
+/// ノードの `/v4/info` を記録する。複数ノードの場合はどれかで使えるものを合わせる。
+pub fn record_capabilities(source_managers: &[String], plugins: &[String]) {
+    let mut caps = CAPABILITIES.write().unwrap_or_else(|e| e.into_inner());
+    let caps = caps.get_or_insert_with(LavalinkCapabilities::default);
+    for m in source_managers {
+        if !caps.source_managers.contains(m) {
+            caps.source_managers.push(m.clone());
+        }
+    }
+    for p in plugins {
+        if !caps.plugins.contains(p) {
+            caps.plugins.push(p.clone());
+        }
+    }
+}
+
+/// 使えるソースだけを候補に出す。
+pub async fn autocomplete_source(
+    _ctx: Context<'_>,
+    partial: &str,
+) -> impl Iterator<Item = AutocompleteChoice> {
+    let partial = partial.trim().to_lowercase();
+    SearchSource::available()
+        .into_iter()
+        .filter(move |s| {
+            partial.is_empty()
+                || s.prefix().starts_with(&partial)
+                || s.label().to_lowercase().contains(&partial)
+        })
+        .map(|s| AutocompleteChoice::new(s.label(), s.prefix()))
+}
+
+/// `source` で検索し、見つかった曲を返す。
+pub async fn search(
+    lavalink: &LavalinkClient,
+    guild_id: GuildId,
+    source: SearchSource,
+    terms: &str,
+) -> Result<Vec<TrackData>, Error> {
+    if !source.is_available() {
+        return Err(Error::from(format!(
+            "{} の検索はこの Lavalink では使えません",
+            source.label()
+        )));
+    }
+    let load = lavalink
+        .load_tracks(guild_id, &source.query(terms))
+        .await
+        .map_err(|e| Error::from(format!("Lavalink search request failed: {e}")))?;
+    match load.data {
+        Some(TrackLoadData::Search(tracks)) => Ok(tracks),
+        Some(TrackLoadData::Track(track)) => Ok(vec![track]),
+        Some(TrackLoadData::Playlist(playlist)) => Ok(playlist.tracks),
+        Some(TrackLoadData::Error(err)) => Err(Error::from(format!(
+            "Lavalink search failed: {}",
+            err.message
+        ))),
+        None => Ok(Vec::new()),
+    }
+}
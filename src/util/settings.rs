@@ -0,0 +1,235 @@
+use std::{path::PathBuf, time::Duration};
+
+use dashmap::DashMap;
+use poise::ChoiceParameter;
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    StorageSettings,
+    util::{
+        alias::Error,
+        queue::QueueLimits,
+        repeat::RepeatMode,
+        search_source::SearchSource,
+        storage::{guild_id_from_path, storage_dir, write_json_atomic},
+    },
+};
+
+pub const DEFAULT_PREFIX: &str = "s!";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, ChoiceParameter, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[name = "日本語"]
+    Japanese,
+    #[name = "English"]
+    English,
+}
+
+/// ギルドごとの設定。
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GuildSettings {
+    /// DJ ロール（未設定なら全員が DJ 扱い）
+    #[serde(default)]
+    pub dj_role: Option<u64>,
+    /// リクエストした本人が自分の曲をスキップ・削除できるか
+    #[serde(default = "default_true")]
+    pub requester_can_manage: bool,
+    /// DJ 以外のスキップを投票制にするか
+    #[serde(default)]
+    pub vote_skip: bool,
+    /// スキップに必要な賛成の割合（聴いている人数に対して）
+    #[serde(default = "default_vote_ratio")]
+    pub vote_skip_ratio: f64,
+    /// プレフィックスコマンドの接頭辞（未設定なら `s!`）
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// `/volume` 未指定時の音量
+    #[serde(default = "default_volume")]
+    pub default_volume: u16,
+    /// `/repeat` 未指定時のリピートモード
+    #[serde(default)]
+    pub default_repeat: RepeatMode,
+    /// キューに入れられる最大曲数（未設定なら無制限）
+    #[serde(default)]
+    pub max_queue_length: Option<usize>,
+    /// 追加できる曲の最大長（秒, 未設定なら無制限）
+    #[serde(default)]
+    pub max_track_secs: Option<u64>,
+    /// 1 人がキューに入れられる最大曲数（未設定なら無制限）
+    #[serde(default)]
+    pub max_tracks_per_user: Option<usize>,
+    /// 同じ URL の曲をキューに重複して入れられるか
+    #[serde(default)]
+    pub allow_duplicates: bool,
+    /// プレイリストから一度に追加する最大曲数
+    #[serde(default = "default_playlist_limit")]
+    pub playlist_limit: usize,
+    /// 保持する再生履歴の件数
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// 再生パネルのボタン操作を受け付ける時間（秒）
+    #[serde(default = "default_panel_timeout")]
+    pub panel_timeout_secs: u64,
+    /// `/queue` などの UI 操作を受け付ける時間（秒）
+    #[serde(default = "default_ui_timeout")]
+    pub ui_timeout_secs: u64,
+    /// 自動退出や再開の案内を送るチャンネル（未設定なら再生パネルのチャンネル）
+    #[serde(default)]
+    pub announce_channel: Option<u64>,
+    #[serde(default)]
+    pub language: Language,
+    /// URL 以外の検索語をどのサービスで検索するか
+    #[serde(default)]
+    pub search_source: SearchSource,
+}
+
+const fn default_vote_ratio() -> f64 {
+    0.5
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+const fn default_volume() -> u16 {
+    100
+}
+
+const fn default_playlist_limit() -> usize {
+    50
+}
+
+const fn default_history_limit() -> usize {
+    50
+}
+
+const fn default_panel_timeout() -> u64 {
+    1800
+}
+
+const fn default_ui_timeout() -> u64 {
+    300
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            dj_role: None,
+            requester_can_manage: true,
+            vote_skip: false,
+            vote_skip_ratio: default_vote_ratio(),
+            prefix: None,
+            default_volume: default_volume(),
+            default_repeat: RepeatMode::Off,
+            max_queue_length: None,
+            max_track_secs: None,
+            max_tracks_per_user: None,
+            allow_duplicates: false,
+            playlist_limit: default_playlist_limit(),
+            history_limit: default_history_limit(),
+            panel_timeout_secs: default_panel_timeout(),
+            ui_timeout_secs: default_ui_timeout(),
+            announce_channel: None,
+            language: Language::Japanese,
+            search_source: SearchSource::YouTube,
+        }
+    }
+}
+
+impl GuildSettings {
+    pub fn dj_role(&self) -> Option<RoleId> {
+        self.dj_role.filter(|&id| id != 0).map(RoleId::new)
+    }
+
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_deref().unwrap_or(DEFAULT_PREFIX)
+    }
+
+    pub fn announce_channel(&self) -> Option<ChannelId> {
+        self.announce_channel
+            .filter(|&id| id != 0)
+            .map(ChannelId::new)
+    }
+
+    pub fn queue_limits(&self) -> QueueLimits {
+        QueueLimits {
+            max_length: self.max_queue_length.filter(|&n| n > 0),
+            max_per_user: self.max_tracks_per_user.filter(|&n| n > 0),
+            max_duration: self
+                .max_track_secs
+                .filter(|&s| s > 0)
+                .map(Duration::from_secs),
+            allow_duplicates: self.allow_duplicates,
+        }
+    }
+
+    pub fn panel_timeout(&self) -> Duration {
+        Duration::from_secs(self.panel_timeout_secs.max(30))
+    }
+
+    pub fn ui_timeout(&self) -> Duration {
+        Duration::from_secs(self.ui_timeout_secs.max(30))
+    }
+}
+
+/// ギルド設定を保持し、変更のたびに `data/settings/<guild>.json` へ書き出す。
+pub struct SettingsStore {
+    dir: Option<PathBuf>,
+    guilds: DashMap<GuildId, GuildSettings>,
+}
+
+impl SettingsStore {
+    /// 保存済みの設定を読み込んで作成する。
+    pub fn load(cfg: Option<&StorageSettings>) -> Self {
+        let dir = storage_dir(cfg).map(|d| d.join("settings"));
+        let guilds = DashMap::new();
+        if let Some(entries) = dir.as_ref().and_then(|d| std::fs::read_dir(d).ok()) {
+            for path in entries.flatten().map(|e| e.path()) {
+                let Some(guild_id) = guild_id_from_path(&path) else {
+                    continue;
+                };
+                match std::fs::read(&path)
+                    .map_err(Error::from)
+                    .and_then(|b| serde_json::from_slice::<GuildSettings>(&b).map_err(Error::from))
+                {
+                    Ok(settings) => {
+                        guilds.insert(guild_id, settings);
+                    }
+                    Err(err) => {
+                        tracing::warn!(path = %path.display(), error = %err, "failed to load guild settings");
+                    }
+                }
+            }
+        }
+        tracing::info!(guilds = guilds.len(), "loaded guild settings");
+        Self { dir, guilds }
+    }
+
+    /// 設定を取得する（未設定なら既定値）。
+    pub fn get(&self, guild_id: GuildId) -> GuildSettings {
+        self.guilds
+            .get(&guild_id)
+            .map(|s| s.value().clone())
+            .unwrap_or_default()
+    }
+
+    /// 設定を変更して保存する。変更後の値を返す。
+    pub async fn update(
+        &self,
+        guild_id: GuildId,
+        f: impl FnOnce(&mut GuildSettings),
+    ) -> Result<GuildSettings, Error> {
+        let updated = {
+            let mut entry = self.guilds.entry(guild_id).or_default();
+            f(entry.value_mut());
+            entry.value().clone()
+        };
+        if let Some(dir) = &self.dir {
+            tokio::fs::create_dir_all(dir).await?;
+            write_json_atomic(&dir.join(format!("{guild_id}.json")), &updated).await?;
+        }
+        Ok(updated)
+    }
+}
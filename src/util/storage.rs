@@ -0,0 +1,383 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use dashmap::{DashMap, DashSet};
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+use crate::{
+    StorageSettings,
+    util::{
+        alias::Error,
+        filters::FilterPreset,
+        queue::MusicQueue,
+        repeat::RepeatMode,
+        track::{TrackMetadata, TrackRequest},
+        types::{HistoryMap, LavalinkPlayingMap, NowPlayingMap, PositionMap, VoiceChannelMap},
+    },
+};
+
+/// 連続した変更をまとめて書き込むための待ち時間。
+const FLUSH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 永続化用の TrackRequest 表現（AuxMetadata のうち表示に使う項目のみ保持）。
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredTrack {
+    pub url: String,
+    pub requested_by: u64,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub album: Option<String>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub source_url: Option<String>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// キューに入った順番（シャッフル中に保存しても元の並びに戻せるように）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_order: Option<i64>,
+}
+
+impl From<&TrackRequest> for StoredTrack {
+    fn from(tr: &TrackRequest) -> Self {
+        Self {
+            url: tr.url.clone(),
+            requested_by: tr.requested_by.get(),
+            title: tr.meta.title.clone(),
+            artist: tr.meta.artist.clone(),
+            album: tr.meta.album.clone(),
+            duration_ms: tr.meta.duration.map(|d| d.as_millis() as u64),
+            source_url: tr.meta.source_url.clone(),
+            thumbnail: tr.meta.thumbnail.clone(),
+            queue_order: None,
+        }
+    }
+}
+
+impl StoredTrack {
+    /// キューの並び順も含めて保存する（ギルドのスナップショット用）。
+    fn with_order(tr: &TrackRequest) -> Self {
+        Self {
+            queue_order: Some(tr.queue_order),
+            ..Self::from(tr)
+        }
+    }
+}
+
+impl From<StoredTrack> for TrackRequest {
+    fn from(st: StoredTrack) -> Self {
+        let mut meta = TrackMetadata::default();
+        meta.title = st.title;
+        meta.artist = st.artist;
+        meta.album = st.album;
+        meta.duration = st.duration_ms.map(Duration::from_millis);
+        meta.source_url = st.source_url;
+        meta.thumbnail = st.thumbnail;
+        Self {
+            url: st.url,
+            requested_by: UserId::new(st.requested_by.max(1)),
+            meta,
+            queue_order: st.queue_order.unwrap_or(0),
+        }
+    }
+}
+
+/// 保存時点の再生位置と接続先（再起動後の再開に使う）。
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredSession {
+    pub voice_channel: u64,
+    #[serde(default)]
+    pub text_channel: Option<u64>,
+    #[serde(default)]
+    pub position_ms: u64,
+}
+
+/// 再起動前に再生していたトラックを再開するための情報。
+#[derive(Clone, Debug)]
+pub struct ResumeSession {
+    pub guild_id: GuildId,
+    pub voice_channel: ChannelId,
+    pub text_channel: Option<ChannelId>,
+    pub position: Duration,
+    pub track: TrackRequest,
+}
+
+/// スナップショット作成時に参照する共有マップ。
+#[derive(Clone)]
+pub struct SnapshotSources {
+    pub queues: Arc<DashMap<GuildId, MusicQueue>>,
+    pub history: HistoryMap,
+    pub playing: LavalinkPlayingMap,
+    pub now_playing: NowPlayingMap,
+    pub voice_channels: VoiceChannelMap,
+    pub positions: PositionMap,
+}
+
+/// ギルド単位で保存する再生状態。
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct GuildSnapshot {
+    #[serde(default)]
+    pub queue: Vec<StoredTrack>,
+    #[serde(default)]
+    pub history: Vec<StoredTrack>,
+    /// 保存時点で再生中だったトラック（復元時はキュー先頭に戻す）。
+    #[serde(default)]
+    pub current: Option<StoredTrack>,
+    #[serde(default)]
+    pub session: Option<StoredSession>,
+    #[serde(default)]
+    pub repeat_mode: Option<RepeatMode>,
+    #[serde(default)]
+    pub shuffle: bool,
+    #[serde(default)]
+    pub fair: bool,
+    #[serde(default)]
+    pub autoplay: bool,
+    #[serde(default)]
+    pub crossfade_secs: u64,
+    #[serde(default)]
+    pub volume: Option<u16>,
+    #[serde(default)]
+    pub filter: FilterPreset,
+    #[serde(default)]
+    pub custom_eq: Vec<f64>,
+}
+
+impl GuildSnapshot {
+    fn capture(guild_id: GuildId, src: &SnapshotSources) -> Self {
+        let mut snapshot = Self::default();
+        if let Some(q) = src.queues.get(&guild_id) {
+            snapshot.queue = q.iter().map(StoredTrack::with_order).collect();
+            snapshot.repeat_mode = q.config.repeat_mode;
+            snapshot.shuffle = q.config.shuffle;
+            snapshot.fair = q.config.fair;
+            snapshot.autoplay = q.config.autoplay;
+            snapshot.crossfade_secs = q.config.crossfade_secs;
+            snapshot.volume = q.config.volume;
+            snapshot.filter = q.config.filter;
+            snapshot.custom_eq = q.config.custom_eq.clone();
+        }
+        if let Some(h) = src.history.get(&guild_id) {
+            snapshot.history = h.iter().map(StoredTrack::from).collect();
+        }
+        snapshot.current = src
+            .playing
+            .get(&guild_id)
+            .map(|e| StoredTrack::with_order(e.value()));
+        if snapshot.current.is_some() {
+            snapshot.session = src.voice_channels.get(&guild_id).map(|vc| StoredSession {
+                voice_channel: vc.get(),
+                text_channel: src.now_playing.get(&guild_id).map(|e| e.value().0.get()),
+                position_ms: src.positions.get(&guild_id).map(|p| *p).unwrap_or(0),
+            });
+        }
+        snapshot
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+            && self.history.is_empty()
+            && self.current.is_none()
+            && self.session.is_none()
+            && self.repeat_mode.is_none()
+            && !self.shuffle
+            && !self.fair
+            && !self.autoplay
+            && self.crossfade_secs == 0
+            && self.volume.is_none()
+            && self.filter == FilterPreset::Off
+    }
+}
+
+/// キュー・履歴・再生設定をギルドごとの JSON ファイルに保存する。
+pub struct MusicStore {
+    dir: Option<PathBuf>,
+    dirty: DashSet<GuildId>,
+    notify: Notify,
+}
+
+impl MusicStore {
+    pub fn from_settings(cfg: Option<&StorageSettings>) -> Self {
+        Self {
+            dir: storage_dir(cfg),
+            dirty: DashSet::new(),
+            notify: Notify::new(),
+        }
+    }
+
+    fn guilds_dir(&self) -> Option<PathBuf> {
+        self.dir.as_ref().map(|d| d.join("guilds"))
+    }
+
+    /// 変更があったギルドを記録し、書き込みタスクを起こす。
+    pub fn mark_dirty(&self, guild_id: GuildId) {
+        if self.dir.is_none() {
+            return;
+        }
+        self.dirty.insert(guild_id);
+        self.notify.notify_one();
+    }
+
+    /// 起動時に保存済みの状態を読み込み、キューと履歴へ復元する。
+    /// 再生途中だったギルドは再開候補として返す。
+    pub fn restore(
+        &self,
+        queues: &DashMap<GuildId, MusicQueue>,
+        history: &HistoryMap,
+    ) -> Vec<ResumeSession> {
+        let mut sessions = Vec::new();
+        let Some(dir) = self.guilds_dir() else {
+            return sessions;
+        };
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return sessions,
+            Err(err) => {
+                tracing::warn!(path = %dir.display(), error = %err, "failed to read storage dir");
+                return sessions;
+            }
+        };
+
+        let mut restored = 0usize;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(guild_id) = guild_id_from_path(&path) else {
+                continue;
+            };
+            let snapshot = match read_snapshot(&path) {
+                Ok(s) => s,
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), error = %err, "failed to load guild snapshot");
+                    continue;
+                }
+            };
+
+            let mut q = queues.entry(guild_id).or_default();
+            q.config.repeat_mode = snapshot.repeat_mode;
+            q.set_shuffle(snapshot.shuffle);
+            q.set_fair(snapshot.fair);
+            q.set_autoplay(snapshot.autoplay);
+            q.set_crossfade(snapshot.crossfade_secs);
+            q.config.volume = snapshot.volume;
+            q.set_filter(snapshot.filter, snapshot.custom_eq);
+            if let Some(current) = snapshot.current {
+                let has_order = current.queue_order.is_some();
+                let track = TrackRequest::from(current);
+                if let Some(session) = snapshot.session.filter(|s| s.voice_channel != 0) {
+                    sessions.push(ResumeSession {
+                        guild_id,
+                        voice_channel: ChannelId::new(session.voice_channel),
+                        text_channel: session.text_channel.filter(|&c| c != 0).map(ChannelId::new),
+                        position: Duration::from_millis(session.position_ms),
+                        track: track.clone(),
+                    });
+                }
+                q.restore_back(track, has_order);
+            }
+            for st in snapshot.queue {
+                let has_order = st.queue_order.is_some();
+                q.restore_back(st.into(), has_order);
+            }
+            drop(q);
+
+            if !snapshot.history.is_empty() {
+                history.insert(
+                    guild_id,
+                    snapshot
+                        .history
+                        .into_iter()
+                        .map(TrackRequest::from)
+                        .collect(),
+                );
+            }
+            restored += 1;
+        }
+
+        tracing::info!(
+            guilds = restored,
+            resumable = sessions.len(),
+            "restored persisted music state"
+        );
+        sessions
+    }
+
+    /// dirty なギルドをまとめて書き出すバックグラウンドタスクを起動する。
+    pub fn spawn_writer(self: &Arc<Self>, sources: SnapshotSources) {
+        let Some(dir) = self.guilds_dir() else {
+            return;
+        };
+        let store = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = tokio::fs::create_dir_all(&dir).await {
+                tracing::error!(path = %dir.display(), error = %err, "failed to create storage dir");
+                return;
+            }
+            loop {
+                store.notify.notified().await;
+                tokio::time::sleep(FLUSH_DEBOUNCE).await;
+
+                let pending = store.dirty.iter().map(|g| *g).collect::<Vec<_>>();
+                for guild_id in pending {
+                    store.dirty.remove(&guild_id);
+                    let snapshot = GuildSnapshot::capture(guild_id, &sources);
+                    let path = dir.join(format!("{guild_id}.json"));
+                    if let Err(err) = write_snapshot(&path, &snapshot).await {
+                        tracing::warn!(guild = %guild_id, error = %err, "failed to persist guild state");
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// 保存先ディレクトリ（無効化されていれば None）。
+pub(crate) fn storage_dir(cfg: Option<&StorageSettings>) -> Option<PathBuf> {
+    match cfg {
+        Some(cfg) if !cfg.enabled => None,
+        Some(cfg) => Some(PathBuf::from(cfg.dir.as_deref().unwrap_or("data"))),
+        None => Some(PathBuf::from("data")),
+    }
+}
+
+pub(crate) fn guild_id_from_path(path: &Path) -> Option<GuildId> {
+    if path.extension().and_then(|e| e.to_str()) != Some("json") {
+        return None;
+    }
+    let id = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+    (id != 0).then(|| GuildId::new(id))
+}
+
+fn read_snapshot(path: &Path) -> Result<GuildSnapshot, Error> {
+    let bytes = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+async fn write_snapshot(path: &Path, snapshot: &GuildSnapshot) -> Result<(), Error> {
+    if snapshot.is_empty() {
+        match tokio::fs::remove_file(path).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        return Ok(());
+    }
+
+    write_json_atomic(path, snapshot).await
+}
+
+/// 途中で落ちても壊れたファイルが残らないよう、一時ファイル経由で置き換える。
+pub(crate) async fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let json = serde_json::to_vec_pretty(value)?;
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, json).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
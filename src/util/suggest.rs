@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use poise::serenity_prelude::{AutocompleteChoice, UserId};
+use tokio::time::{sleep, timeout};
+use url::Url;
+
+use crate::util::{
+    alias::Context,
+    lavalink_player::apply_track_metadata,
+    music_ui::{format_duration, truncate_chars},
+    search_source::{self, SearchSource},
+    track::TrackRequest,
+};
+
+/// 入力が止まるまで待つ時間。打鍵ごとに届く古いリクエストでは検索しない。
+const DEBOUNCE: Duration = Duration::from_millis(350);
+/// Discord は 3 秒以内に応答しないと候補を捨てるので、それより短く打ち切る。
+const SEARCH_TIMEOUT: Duration = Duration::from_millis(2000);
+const CACHE_TTL: Duration = Duration::from_secs(300);
+const CACHE_CAPACITY: usize = 256;
+const MIN_SEARCH_CHARS: usize = 2;
+const MAX_SEARCH_RESULTS: usize = 10;
+const MAX_HISTORY_RESULTS: usize = 5;
+/// 候補の名前と値はどちらも 100 文字まで。
+const CHOICE_MAX_CHARS: usize = 100;
+
+#[derive(Clone)]
+struct Suggestion {
+    name: String,
+    value: String,
+}
+
+struct CachedSearch {
+    at: Instant,
+    suggestions: Vec<Suggestion>,
+}
+
+static SEARCH_CACHE: Lazy<Mutex<HashMap<String, CachedSearch>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// ユーザーごとの最新の入力の世代番号。検索が終わったら消す。
+static GENERATIONS: Lazy<DashMap<UserId, u64>> = Lazy::new(DashMap::new);
+
+fn label(title: &str, duration: Option<Duration>) -> String {
+    let suffix = format!(" — {}", format_duration(duration));
+    let title = truncate_chars(title, CHOICE_MAX_CHARS - suffix.chars().count());
+    format!("{title}{suffix}")
+}
+
+/// 値が 100 文字を超える URL は選ばれても再生できないので、代わりに曲名で検索させる。
+fn choice_value(url: &str, fallback: &str) -> String {
+    if url.chars().count() <= CHOICE_MAX_CHARS {
+        url.to_string()
+    } else {
+        truncate_chars(fallback, CHOICE_MAX_CHARS)
+    }
+}
+
+fn cache_get(key: &str) -> Option<Vec<Suggestion>> {
+    let cache = SEARCH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(key)
+        .filter(|entry| entry.at.elapsed() < CACHE_TTL)
+        .map(|entry| entry.suggestions.clone())
+}
+
+fn cache_put(key: String, suggestions: Vec<Suggestion>) {
+    let mut cache = SEARCH_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|_, entry| entry.at.elapsed() < CACHE_TTL);
+    if cache.len() >= CACHE_CAPACITY {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.at)
+            .map(|(key, _)| key.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        key,
+        CachedSearch {
+            at: Instant::now(),
+            suggestions,
+        },
+    );
+}
+
+/// 本人がリクエストした曲の再生履歴から、入力に合うものを新しい順に返す。
+fn history_suggestions(ctx: Context<'_>, user: UserId, partial: &str) -> Vec<Suggestion> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+    let Some(history) = ctx.data().history.get(&guild_id) else {
+        return Vec::new();
+    };
+    let needle = partial.to_lowercase();
+    let mut out: Vec<Suggestion> = Vec::new();
+    for req in history.iter().rev().filter(|r| r.requested_by == user) {
+        let title = req.meta.title.as_deref().unwrap_or(&req.url);
+        if !needle.is_empty() && !title.to_lowercase().contains(&needle) {
+            continue;
+        }
+        let url = req.meta.source_url.as_deref().unwrap_or(&req.url);
+        if out.iter().any(|s| s.value == url) {
+            continue;
+        }
+        out.push(Suggestion {
+            name: label(&format!("🕘 {title}"), req.meta.duration),
+            value: choice_value(url, title),
+        });
+        if out.len() >= MAX_HISTORY_RESULTS {
+            break;
+        }
+    }
+    out
+}
+
+/// 同じコマンドで選ばれている `source`。未指定や解釈できない値なら `None`。
+fn chosen_source(ctx: Context<'_>) -> Option<SearchSource> {
+    let poise::Context::Application(actx) = ctx else {
+        return None;
+    };
+    actx.interaction
+        .data
+        .options
+        .iter()
+        .find(|o| o.name == "source")
+        .and_then(|o| o.value.as_str())
+        .and_then(|v| v.parse().ok())
+}
+
+async fn search_suggestions(
+    ctx: Context<'_>,
+    user: UserId,
+    source: Option<SearchSource>,
+    partial: &str,
+) -> Vec<Suggestion> {
+    let (Some(guild_id), Some(lavalink)) = (ctx.guild_id(), ctx.data().lavalink.clone()) else {
+        return Vec::new();
+    };
+    let source = source.unwrap_or_else(|| ctx.data().settings.get(guild_id).search_source);
+    let key = format!("{}:{}", source.prefix(), partial.to_lowercase());
+    if let Some(hit) = cache_get(&key) {
+        return hit;
+    }
+
+    let generation = {
+        let mut current = GENERATIONS.entry(user).or_insert(0);
+        *current += 1;
+        *current
+    };
+    sleep(DEBOUNCE).await;
+    if GENERATIONS.get(&user).is_some_and(|g| *g != generation) {
+        // 続けて入力されたので、このリクエストの候補は使われない
+        return Vec::new();
+    }
+
+    let result = timeout(
+        SEARCH_TIMEOUT,
+        search_source::search(&lavalink, guild_id, source, partial),
+    )
+    .await;
+    // 後から来た入力が世代を進めていれば、そちらが消す
+    GENERATIONS.remove_if(&user, |_, g| *g == generation);
+    let found = match result {
+        Ok(Ok(tracks)) => tracks,
+        Ok(Err(e)) => {
+            tracing::debug!(guild = guild_id.get(), error = %e, "autocomplete search failed");
+            return Vec::new();
+        }
+        Err(_) => {
+            tracing::debug!(guild = guild_id.get(), "autocomplete search timed out");
+            return Vec::new();
+        }
+    };
+
+    let suggestions: Vec<Suggestion> = found
+        .iter()
+        .take(MAX_SEARCH_RESULTS)
+        .map(|track| {
+            let mut req = TrackRequest::new(partial.to_string(), user);
+            apply_track_metadata(&mut req, track);
+            let title = req.meta.title.as_deref().unwrap_or(partial);
+            Suggestion {
+                name: label(title, req.meta.duration),
+                value: choice_value(&req.url, title),
+            }
+        })
+        .collect();
+    cache_put(key, suggestions.clone());
+    suggestions
+}
+
+/// `/play` の `query` の候補。本人の再生履歴と、`source`（省略時は既定の検索ソース）での検索結果を出す。
+pub async fn autocomplete_query(
+    ctx: Context<'_>,
+    partial: &str,
+) -> impl Iterator<Item = AutocompleteChoice> {
+    let user = ctx.author().id;
+    let partial = partial.trim();
+    let mut suggestions = history_suggestions(ctx, user, partial);
+    if partial.chars().count() >= MIN_SEARCH_CHARS && Url::parse(partial).is_err() {
+        for s in search_suggestions(ctx, user, chosen_source(ctx), partial).await {
+            if !suggestions.iter().any(|h| h.value == s.value) {
+                suggestions.push(s);
+            }
+        }
+    }
+    suggestions
+        .into_iter()
+        .take(25)
+        .map(|s| AutocompleteChoice::new(s.name, s.value))
+}
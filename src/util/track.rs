@@ -15,6 +15,40 @@ fn is_youtube(u: &str) -> bool {
     u.contains("youtube.com") || u.contains("youtu.be") || u.contains("m.youtube.com")
 }
 
+/// YouTube の URL から動画 ID を取り出す。
+pub(crate) fn youtube_video_id(raw: &str) -> Option<String> {
+    let url = Url::parse(raw).ok()?;
+    let host = url.host_str().unwrap_or_default();
+    if host.contains("youtube.com") || host.contains("m.youtube.com") {
+        let id = url
+            .query_pairs()
+            .find_map(|(k, v)| (k == "v").then_some(v))?;
+        let id = id.trim();
+        if id.is_empty() {
+            return None;
+        }
+        return Some(id.to_string());
+    }
+    if host.contains("youtu.be") {
+        let seg = url.path_segments().and_then(|mut s| s.next())?;
+        let seg = seg.trim();
+        if seg.is_empty() {
+            return None;
+        }
+        return Some(seg.to_string());
+    }
+    None
+}
+
+fn normalize_youtube_key(url: &str) -> Option<String> {
+    youtube_video_id(url).map(|id| format!("yt:{id}"))
+}
+
+/// 同じ曲を指す URL をまとめるためのキー（YouTube は動画 ID で正規化）。
+pub(crate) fn metadata_lookup_key(url: &str) -> String {
+    normalize_youtube_key(url).unwrap_or_else(|| format!("url:{url}"))
+}
+
 /// 再生リクエストの元情報（入力文字列と取得済みメタデータ）を保持する。
 /// 検索語の場合も最終的な source_url を解決して `url` に書き戻す。
 #[derive(Clone, Debug)]
@@ -22,14 +56,22 @@ pub struct TrackRequest {
     pub url: String,
     pub requested_by: serenity_prelude::UserId,
     pub meta: AuxMetadata,
+    /// キューに入った順番（シャッフル解除時に元の並びへ戻すため `MusicQueue` が付ける）
+    pub(crate) queue_order: i64,
 }
 
 impl TrackRequest {
+    /// 重複判定用のキー（解決済みの URL があればそれを使う）。
+    pub fn dedupe_key(&self) -> String {
+        metadata_lookup_key(self.meta.source_url.as_deref().unwrap_or(&self.url))
+    }
+
     pub fn new(url: String, requested_by: UserId) -> Self {
         Self {
             url,
             requested_by,
             meta: AuxMetadata::default(),
+            queue_order: 0,
         }
     }
 
@@ -100,6 +142,7 @@ impl TrackRequest {
             url,
             requested_by,
             meta,
+            queue_order: 0,
         })
     }
 }
@@ -1,9 +1,10 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64};
 
-use dashmap::DashMap;
-use poise::serenity_prelude::{ChannelId, GuildId, MessageId};
+use dashmap::{DashMap, DashSet};
+use lavalink_rs::model::track::TrackData;
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
 
 use crate::util::track::TrackRequest;
 
@@ -11,3 +12,17 @@ pub type LavalinkPlayingMap = Arc<DashMap<GuildId, TrackRequest>>;
 pub type TransitionFlags = Arc<DashMap<GuildId, Arc<AtomicBool>>>;
 pub type HistoryMap = Arc<DashMap<GuildId, VecDeque<TrackRequest>>>;
 pub type NowPlayingMap = Arc<DashMap<GuildId, (ChannelId, MessageId)>>;
+pub type VoiceChannelMap = Arc<DashMap<GuildId, ChannelId>>;
+pub type PositionMap = Arc<DashMap<GuildId, u64>>;
+/// 次曲として先読み解決済みのトラック（キー: ギルド、値: 元の URL と TrackData）
+pub type PrefetchMap = Arc<DashMap<GuildId, (String, TrackData)>>;
+/// フェード処理の世代番号（曲が切り替わるたびに進め、古いフェードを打ち切る）
+pub type FadeGenerations = Arc<DashMap<GuildId, Arc<AtomicU64>>>;
+/// 再生パネルの定期更新タスクが動いているギルド
+pub type PanelUpdaters = Arc<DashSet<GuildId>>;
+/// 自動退出タイマーの識別子（置き換えられたタイマーは発火しても何もしない）
+pub type IdleTimers = Arc<DashMap<GuildId, u64>>;
+/// 無人になったため自動で一時停止したギルド
+pub type AutoPaused = Arc<DashSet<GuildId>>;
+/// スキップ投票（キー: ギルド、値: 対象トラックの URL と投票者）
+pub type VoteMap = Arc<DashMap<GuildId, (String, HashSet<UserId>)>>;
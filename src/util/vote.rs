@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use poise::serenity_prelude::{Cache, GuildId, UserId};
+
+use crate::{
+    Data,
+    util::{
+        idle::listeners_in_channel,
+        types::{LavalinkPlayingMap, VoiceChannelMap, VoteMap},
+    },
+};
+
+pub enum VoteOutcome {
+    /// 必要数に達した（呼び出し側でスキップする）
+    Passed,
+    Recorded {
+        votes: usize,
+        needed: usize,
+    },
+    AlreadyVoted {
+        votes: usize,
+        needed: usize,
+    },
+    NotListening,
+    NothingPlaying,
+}
+
+impl VoteOutcome {
+    pub fn message(&self) -> String {
+        match self {
+            Self::Passed => "🗳️ スキップ投票が成立しました".to_string(),
+            Self::Recorded { votes, needed } => {
+                format!("🗳️ スキップに投票しました ({votes}/{needed})")
+            }
+            Self::AlreadyVoted { votes, needed } => {
+                format!("🗳️ すでに投票済みです ({votes}/{needed})")
+            }
+            Self::NotListening => {
+                "🗳️ 投票するにはボットと同じボイスチャンネルに参加してください".to_string()
+            }
+            Self::NothingPlaying => "再生中の曲がありません".to_string(),
+        }
+    }
+}
+
+/// 聴いている人数に対して必要な票数。
+fn needed_votes(listeners: usize, ratio: f64) -> usize {
+    ((listeners as f64 * ratio.clamp(0.0, 1.0)).ceil() as usize).max(1)
+}
+
+/// 現在の曲への有効票数（チャンネルにいる人の票のみ）と必要票数。
+pub fn tally(
+    cache: &Cache,
+    voice_channels: &VoiceChannelMap,
+    playing: &LavalinkPlayingMap,
+    votes: &VoteMap,
+    guild_id: GuildId,
+    ratio: f64,
+) -> Option<(usize, usize)> {
+    let current = playing.get(&guild_id)?.value().url.clone();
+    let channel_id = *voice_channels.get(&guild_id)?.value();
+    let listeners = listeners_in_channel(cache, guild_id, channel_id)?;
+    let entry = votes.get(&guild_id)?;
+    let (url, voters) = entry.value();
+    if *url != current {
+        return None;
+    }
+    let count = voters.iter().filter(|u| listeners.contains(u)).count();
+    Some((count, needed_votes(listeners.len(), ratio)))
+}
+
+/// スキップに一票を入れ、必要数に達したか判定する。
+pub fn cast_vote(cache: &Cache, data: &Data, guild_id: GuildId, user_id: UserId) -> VoteOutcome {
+    let Some(current) = data
+        .lavalink_playing
+        .get(&guild_id)
+        .map(|e| e.value().url.clone())
+    else {
+        return VoteOutcome::NothingPlaying;
+    };
+    let Some(channel_id) = data.voice_channels.get(&guild_id).map(|e| *e.value()) else {
+        return VoteOutcome::NothingPlaying;
+    };
+    let Some(listeners) = listeners_in_channel(cache, guild_id, channel_id) else {
+        return VoteOutcome::NotListening;
+    };
+    if !listeners.contains(&user_id) {
+        return VoteOutcome::NotListening;
+    }
+
+    let ratio = data.settings.get(guild_id).vote_skip_ratio;
+    let needed = needed_votes(listeners.len(), ratio);
+    let mut entry = data
+        .votes
+        .entry(guild_id)
+        .or_insert_with(|| (current.clone(), HashSet::new()));
+    let (url, voters) = entry.value_mut();
+    if *url != current {
+        *url = current;
+        voters.clear();
+    }
+    let inserted = voters.insert(user_id);
+    // 退出した人の票は数えない
+    voters.retain(|u| listeners.contains(u));
+    let votes = voters.len();
+
+    if votes >= needed {
+        drop(entry);
+        data.votes.remove(&guild_id);
+        VoteOutcome::Passed
+    } else if inserted {
+        VoteOutcome::Recorded { votes, needed }
+    } else {
+        VoteOutcome::AlreadyVoted { votes, needed }
+    }
+}